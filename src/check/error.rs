@@ -0,0 +1,74 @@
+use std::fmt::{Display, Formatter};
+use std::error::Error;
+
+use crate::client::CrawlClientError;
+use crate::queue::QueueConfigurationError;
+
+/// Why a link is broken: its target could not be fetched, or it has no anchor for the
+/// fragment of the link.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LinkError {
+    ClientError(CrawlClientError),
+    MissingAnchor(String),
+}
+
+impl LinkError {
+    /// A short name for the kind of error, the same for every error of the kind.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LinkError::ClientError(err) => err.kind(),
+            LinkError::MissingAnchor(_) => "missing-anchor",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            LinkError::ClientError(err) => err.message(),
+            LinkError::MissingAnchor(fragment) => format!("The page has no anchor named {}", fragment),
+        }
+    }
+}
+
+impl Display for LinkError {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(fmt, "{}", self.message())
+    }
+}
+
+impl Error for LinkError {}
+
+impl From<CrawlClientError> for LinkError {
+    fn from(err: CrawlClientError) -> Self {
+        LinkError::ClientError(err)
+    }
+}
+
+#[derive(Debug)]
+pub enum KnownBrokenError {
+    IOError(String),
+    InvalidPattern(String),
+}
+
+impl Display for KnownBrokenError {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> std::fmt::Result {
+        let display_string = match self {
+            KnownBrokenError::IOError(reason) => format!("Could not read the known broken links: {}", reason),
+            KnownBrokenError::InvalidPattern(reason) => format!("Invalid known broken link: {}", reason),
+        };
+        write!(fmt, "{}", display_string)
+    }
+}
+
+impl Error for KnownBrokenError {}
+
+impl From<std::io::Error> for KnownBrokenError {
+    fn from(err: std::io::Error) -> Self {
+        KnownBrokenError::IOError(err.to_string())
+    }
+}
+
+impl From<QueueConfigurationError> for KnownBrokenError {
+    fn from(err: QueueConfigurationError) -> Self {
+        KnownBrokenError::InvalidPattern(err.to_string())
+    }
+}
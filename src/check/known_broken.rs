@@ -0,0 +1,68 @@
+use std::convert::TryFrom;
+
+use crate::check::KnownBrokenError;
+use crate::queue::UrlPattern;
+
+/// A known broken link, as the URL itself or as a pattern.
+enum KnownLink {
+    Exact(String),
+    Pattern(UrlPattern),
+}
+
+impl KnownLink {
+    fn matches(&self, link: &str) -> bool {
+        match self {
+            KnownLink::Exact(known) => known == link,
+            KnownLink::Pattern(pattern) => pattern.matches(link),
+        }
+    }
+}
+
+/// Links known to be broken, which are reported without failing the check. Read one per
+/// line, skipping blank lines and lines starting with `#`. Lines are URLs matched exactly,
+/// unless they start with `glob:` or `regex:` to be a pattern like the ones of `--include`.
+#[derive(Default)]
+pub struct KnownBroken {
+    links: Vec<KnownLink>,
+}
+
+impl KnownBroken {
+    pub fn load(path: &str) -> Result<KnownBroken, KnownBrokenError> {
+        KnownBroken::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(content: &str) -> Result<KnownBroken, KnownBrokenError> {
+        let links = content.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| if line.starts_with("glob:") || line.starts_with("regex:") {
+                UrlPattern::try_from(line).map(KnownLink::Pattern)
+            } else {
+                Ok(KnownLink::Exact(line.to_string()))
+            })
+            .collect::<Result<Vec<KnownLink>, _>>()?;
+        Ok(KnownBroken { links })
+    }
+
+    pub fn contains(&self, link: &str) -> bool {
+        self.links.iter().any(|known| known.matches(link))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::check::KnownBroken;
+
+    #[test]
+    fn should_match_links_exactly_and_prefixed_patterns_skipping_comments() {
+        let known = KnownBroken::parse("# flaky\nhttps://domain.com/old?page=1\nhttps://domain.com/[draft]\n\nglob:https://twitter.com/**\nregex:^https://example\\.com/v[0-9]+/\n").unwrap();
+
+        assert!(known.contains("https://domain.com/old?page=1"));
+        assert!(known.contains("https://domain.com/[draft]"));
+        assert!(!known.contains("https://domain.com/old?page=2"));
+        assert!(!known.contains("https://domain.com/d"));
+        assert!(known.contains("https://twitter.com/crawly/status/1"));
+        assert!(known.contains("https://example.com/v2/intro"));
+        assert!(!known.contains("https://domain.com/new"))
+    }
+}
@@ -0,0 +1,95 @@
+use std::str::FromStr;
+
+use futures::StreamExt;
+use hyper::{Body, Method, Request, Uri};
+
+use crate::check::LinkOutcome;
+use crate::client::{CrawlClientError, HttpClient};
+
+/// Checks links outside the crawl without visiting them: a HEAD request, then a GET for
+/// servers that do not answer HEAD requests well. Redirects are not followed, and links on
+/// the target are not looked at.
+pub struct LinkChecker {
+    client: HttpClient,
+    concurrency: usize,
+}
+
+impl LinkChecker {
+    pub fn new(client: HttpClient, concurrency: usize) -> LinkChecker {
+        LinkChecker { client, concurrency: concurrency.max(1) }
+    }
+
+    pub async fn check(&self, link: &str) -> LinkOutcome {
+        let status = match self.status(Method::HEAD, link).await? {
+            status if status >= 400 => self.status(Method::GET, link).await?,
+            status => status,
+        };
+        if status >= 400 {
+            Err(CrawlClientError::HttpError(status).into())
+        } else {
+            Ok(status)
+        }
+    }
+
+    /// Checks the links, `concurrency` at a time.
+    pub async fn check_all(&self, links: Vec<String>) -> Vec<(String, LinkOutcome)> {
+        futures::stream::iter(links)
+            .map(|link| async move {
+                let outcome = self.check(&link).await;
+                (link, outcome)
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await
+    }
+
+    async fn status(&self, method: Method, link: &str) -> Result<u16, CrawlClientError> {
+        let request = Request::builder()
+            .method(method)
+            .uri(Uri::from_str(link)?)
+            .body(Body::empty())
+            .map_err(|_| CrawlClientError::InvalidUri)?;
+        Ok(self.client.request(request).await?.status().as_u16())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use wiremock::matchers::{method, path};
+
+    use crate::check::{LinkChecker, LinkError};
+    use crate::client::{AddressPolicy, create_http_client, CrawlClientError};
+
+    #[tokio::test]
+    async fn should_fall_back_to_get_when_head_is_not_answered() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD")).and(path("/page"))
+            .respond_with(ResponseTemplate::new(405))
+            .mount(&server).await;
+        Mock::given(method("GET")).and(path("/page"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server).await;
+        let checker = LinkChecker::new(create_http_client(AddressPolicy::AllowAll), 1);
+
+        assert_eq!(checker.check(&format!("{}/page", server.uri())).await, Ok(200))
+    }
+
+    #[tokio::test]
+    async fn should_report_links_missing_for_both_requests_as_broken() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD")).and(path("/moved"))
+            .respond_with(ResponseTemplate::new(301).insert_header("Location", "/missing"))
+            .mount(&server).await;
+        let checker = LinkChecker::new(create_http_client(AddressPolicy::AllowAll), 2);
+
+        let mut outcomes = checker.check_all(vec![format!("{}/moved", server.uri()), format!("{}/missing", server.uri())]).await;
+        outcomes.sort_by(|first, second| first.0.cmp(&second.0));
+
+        assert_eq!(outcomes, vec![
+            (format!("{}/missing", server.uri()), Err(LinkError::ClientError(CrawlClientError::HttpError(404)))),
+            (format!("{}/moved", server.uri()), Ok(301)),
+        ])
+    }
+}
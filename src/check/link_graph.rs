@@ -0,0 +1,206 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+use percent_encoding::percent_decode_str;
+use url::Url;
+
+use crate::check::LinkError;
+use crate::link::split_fragment;
+use crate::page::PageResult;
+
+/// What came of checking a link: the status it responded with, or why it is broken.
+pub type LinkOutcome = Result<u16, LinkError>;
+
+/// A page linking to a target, with the text of the link.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Referrer {
+    pub page: String,
+    pub text: String,
+}
+
+/// A checked link with every page linking to it. Seeds have no referrers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CheckedLink {
+    pub url: String,
+    pub outcome: LinkOutcome,
+    pub referrers: Vec<Referrer>,
+}
+
+impl CheckedLink {
+    pub fn is_broken(&self) -> bool {
+        self.outcome.is_err()
+    }
+}
+
+impl Display for CheckedLink {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.outcome {
+            Ok(status) => writeln!(f, "{}: {}", self.url, status)?,
+            Err(err) => writeln!(f, "{}: {}", self.url, err.message())?,
+        }
+        for referrer in &self.referrers {
+            writeln!(f, "  linked from {} as {:?}", referrer.page, referrer.text)?;
+        }
+        Ok(())
+    }
+}
+
+/// Every link of a crawl, from the pages it was found on to its target, and what came of
+/// visiting or checking each target. Only http and https links are kept. Links with a
+/// fragment are broken when their target is, or when a page visited by the crawl has no
+/// anchor for the fragment.
+#[derive(Debug, Default)]
+pub struct LinkGraph {
+    referrers: BTreeMap<String, Vec<Referrer>>,
+    outcomes: HashMap<String, LinkOutcome>,
+    fragments: HashMap<String, HashSet<String>>,
+}
+
+impl LinkGraph {
+    /// Records how the visit to the page went, its anchors and the links found on it.
+    pub fn record(&mut self, page: &PageResult) {
+        let outcome = match &page.error {
+            Some(err) => Err(err.clone().into()),
+            None => Ok(page.status.unwrap_or(200)),
+        };
+        if outcome.is_ok() {
+            self.fragments.insert(page.url.clone(), page.fragments.iter().cloned().collect());
+        }
+        self.outcomes.insert(page.url.clone(), outcome);
+        for link in page.outlinks.iter().filter(|link| is_checkable(&link.url)) {
+            let referrer = Referrer { page: page.url.clone(), text: link.text.clone() };
+            let referrers = self.referrers.entry(link.url.clone()).or_default();
+            if !referrers.contains(&referrer) {
+                referrers.push(referrer);
+            }
+        }
+    }
+
+    /// Records what came of checking a link the crawl did not visit.
+    pub fn checked(&mut self, link: &str, outcome: LinkOutcome) {
+        self.outcomes.insert(link.to_string(), outcome);
+    }
+
+    /// The links found during the crawl that were neither visited nor checked, without their
+    /// fragments.
+    pub fn unchecked(&self) -> Vec<String> {
+        self.referrers.keys()
+            .map(|link| split_fragment(link).0)
+            .filter(|link| !self.outcomes.contains_key(*link))
+            .collect::<BTreeSet<&str>>()
+            .into_iter()
+            .map(|link| link.to_string())
+            .collect()
+    }
+
+    /// Every visited or checked link, and every link with a fragment to one, in order.
+    pub fn links(&self) -> Vec<CheckedLink> {
+        let mut links: Vec<CheckedLink> = self.outcomes.iter()
+            .map(|(url, outcome)| CheckedLink {
+                url: url.clone(),
+                outcome: outcome.clone(),
+                referrers: self.referrers.get(url).cloned().unwrap_or_default(),
+            })
+            .collect();
+        for (url, referrers) in &self.referrers {
+            if let (target, Some(fragment)) = split_fragment(url) {
+                if let Some(outcome) = self.outcomes.get(target) {
+                    links.push(CheckedLink { url: url.clone(), outcome: self.anchor(target, fragment, outcome), referrers: referrers.clone() });
+                }
+            }
+        }
+        links.sort_by(|first, second| first.url.cmp(&second.url));
+        links
+    }
+
+    pub fn broken(&self) -> Vec<CheckedLink> {
+        self.links().into_iter().filter(|link| link.is_broken()).collect()
+    }
+}
+
+impl LinkGraph {
+    /// What came of a link to the fragment of the target. Fragments of targets the crawl did
+    /// not visit cannot be checked, and are taken to be there.
+    fn anchor(&self, target: &str, fragment: &str, outcome: &LinkOutcome) -> LinkOutcome {
+        let status = outcome.clone()?;
+        match self.fragments.get(target) {
+            Some(fragments) if !fragments.contains(percent_decode_str(fragment).decode_utf8_lossy().as_ref()) =>
+                Err(LinkError::MissingAnchor(fragment.to_string())),
+            _ => Ok(status),
+        }
+    }
+}
+
+fn is_checkable(link: &str) -> bool {
+    Url::parse(link)
+        .map(|url| url.scheme() == "http" || url.scheme() == "https")
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::check::{LinkError, LinkGraph, Referrer};
+    use crate::client::CrawlClientError;
+    use crate::page::{Link, PageResult};
+    use crate::queue::FrontierEntry;
+
+    fn graph() -> LinkGraph {
+        let seed = FrontierEntry::seed("https://domain.com/");
+        let mut home = PageResult::new(&seed);
+        home.status = Some(200);
+        home.outlinks = vec![
+            Link::new("https://domain.com/missing", "Missing"),
+            Link::new("https://domain.com/missing", "Missing"),
+            Link::new("https://elsewhere.com/", "Elsewhere"),
+            Link::new("mailto:me@domain.com", "Mail"),
+        ];
+        let missing = PageResult::failed(&FrontierEntry::discovered("https://domain.com/missing", &seed, "Missing"), CrawlClientError::HttpError(404));
+        let mut graph = LinkGraph::default();
+        graph.record(&home);
+        graph.record(&missing);
+        graph
+    }
+
+    #[test]
+    fn should_leave_links_the_crawl_did_not_visit_to_be_checked() {
+        assert_eq!(graph().unchecked(), vec!["https://elsewhere.com/".to_string()])
+    }
+
+    #[test]
+    fn should_report_broken_links_with_the_pages_linking_to_them() {
+        let mut graph = graph();
+        graph.checked("https://elsewhere.com/", Err(CrawlClientError::ConnectionError.into()));
+
+        let broken = graph.broken();
+
+        assert_eq!(broken.iter().map(|link| link.url.as_str()).collect::<Vec<&str>>(), vec!["https://domain.com/missing", "https://elsewhere.com/"]);
+        assert_eq!(broken[0].referrers, vec![Referrer { page: "https://domain.com/".to_string(), text: "Missing".to_string() }]);
+        assert_eq!(broken[0].to_string(), "https://domain.com/missing: The page responded with status 404\n  linked from https://domain.com/ as \"Missing\"\n");
+        assert_eq!(graph.links().len(), 3)
+    }
+
+    #[test]
+    fn should_report_links_to_missing_anchors_of_visited_pages() {
+        let seed = FrontierEntry::seed("https://domain.com/");
+        let mut home = PageResult::new(&seed);
+        home.fragments = vec!["top".to_string(), "café".to_string()];
+        home.outlinks = vec![
+            Link::new("https://domain.com/#top", "Top"),
+            Link::new("https://domain.com/#caf%C3%A9", "Café"),
+            Link::new("https://domain.com/#gone", "Gone"),
+            Link::new("https://elsewhere.com/#anything", "Elsewhere"),
+        ];
+        let mut graph = LinkGraph::default();
+        graph.record(&home);
+
+        assert_eq!(graph.unchecked(), vec!["https://elsewhere.com/".to_string()]);
+        graph.checked("https://elsewhere.com/", Ok(200));
+        let broken = graph.broken();
+
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].url, "https://domain.com/#gone");
+        assert_eq!(broken[0].outcome, Err(LinkError::MissingAnchor("gone".to_string())));
+        assert_eq!(broken[0].referrers, vec![Referrer { page: "https://domain.com/".to_string(), text: "Gone".to_string() }]);
+        assert_eq!(graph.links().len(), 6)
+    }
+}
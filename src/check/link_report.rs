@@ -0,0 +1,236 @@
+use std::io::{self, Write};
+
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::check::{CheckedLink, KnownBroken};
+
+const RULE_ID: &str = "broken-link";
+
+/// How much a broken link matters. Only errors fail the check; warnings and notes are
+/// reported without failing it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    fn sarif_level(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+}
+
+/// How the results of a check are written.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReportFormat {
+    /// Every broken link with the pages linking to it.
+    Text,
+    /// A JUnit XML test suite with one test case per checked link.
+    Junit,
+    /// A SARIF log with one result per broken link on each page linking to it.
+    Sarif,
+}
+
+/// The results of a check, with known broken links set apart from the others.
+pub struct LinkReport<'a> {
+    links: &'a [CheckedLink],
+    known_broken: &'a KnownBroken,
+    severity: Severity,
+}
+
+impl<'a> LinkReport<'a> {
+    pub fn new(links: &'a [CheckedLink], known_broken: &'a KnownBroken, severity: Severity) -> LinkReport<'a> {
+        LinkReport { links, known_broken, severity }
+    }
+
+    /// The broken links not known to be broken.
+    pub fn broken(&self) -> Vec<&'a CheckedLink> {
+        self.links.iter().filter(|link| link.is_broken() && !self.is_known(link)).collect()
+    }
+
+    /// The broken links known to be broken.
+    pub fn known(&self) -> Vec<&'a CheckedLink> {
+        self.links.iter().filter(|link| link.is_broken() && self.is_known(link)).collect()
+    }
+
+    /// Whether the check fails, with broken links of error severity.
+    pub fn fails(&self) -> bool {
+        self.severity == Severity::Error && !self.broken().is_empty()
+    }
+
+    pub fn write<W: Write>(&self, format: ReportFormat, mut writer: W) -> io::Result<()> {
+        match format {
+            ReportFormat::Text => self.broken().iter().try_for_each(|link| write!(writer, "{}", link))?,
+            ReportFormat::Junit => write!(writer, "{}", self.junit())?,
+            ReportFormat::Sarif => writeln!(writer, "{}", serde_json::to_string_pretty(&self.sarif())?)?,
+        }
+        writer.flush()
+    }
+
+    fn is_known(&self, link: &CheckedLink) -> bool {
+        self.known_broken.contains(&link.url)
+    }
+
+    fn junit(&self) -> String {
+        let failures = if self.severity == Severity::Error { self.broken().len() } else { 0 };
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n<testsuite name=\"crawly check\" tests=\"{}\" failures=\"{}\" skipped=\"{}\">\n",
+            self.links.len(), failures, self.known().len());
+        for link in self.links {
+            let host = Url::parse(&link.url).ok().and_then(|url| url.host_str().map(|host| host.to_string())).unwrap_or_default();
+            xml.push_str(&format!("  <testcase classname=\"{}\" name=\"{}\"", xml_escape(&host), xml_escape(&link.url)));
+            let error = match &link.outcome {
+                Ok(_) => {
+                    xml.push_str("/>\n");
+                    continue;
+                }
+                Err(err) => err,
+            };
+            let message = xml_escape(&error.message());
+            let referrers = xml_escape(&referrers(link));
+            if self.is_known(link) {
+                xml.push_str(&format!(">\n    <skipped message=\"known broken: {}\"/>\n", message));
+            } else if self.severity == Severity::Error {
+                xml.push_str(&format!(">\n    <failure type=\"{}\" message=\"{}\">{}</failure>\n", error.kind(), message, referrers));
+            } else {
+                xml.push_str(&format!(">\n    <system-out>{}\n{}</system-out>\n", message, referrers));
+            }
+            xml.push_str("  </testcase>\n");
+        }
+        xml.push_str("</testsuite>\n</testsuites>\n");
+        xml
+    }
+
+    fn sarif(&self) -> Value {
+        let level = self.severity.sarif_level();
+        let mut results = vec![];
+        for link in self.links {
+            let error = match &link.outcome {
+                Ok(_) => continue,
+                Err(err) => err,
+            };
+            let locations: Vec<(&str, Option<&str>)> = if link.referrers.is_empty() {
+                vec![(&link.url, None)]
+            } else {
+                link.referrers.iter().map(|referrer| (referrer.page.as_str(), Some(referrer.text.as_str()))).collect()
+            };
+            for (page, text) in locations {
+                let message = match text {
+                    Some(text) => format!("Broken link to {} ({:?}): {}", link.url, text, error.message()),
+                    None => format!("Broken link {}: {}", link.url, error.message()),
+                };
+                let mut result = json!({
+                    "ruleId": RULE_ID,
+                    "level": level,
+                    "message": { "text": message },
+                    "locations": [{ "physicalLocation": { "artifactLocation": { "uri": page } } }],
+                });
+                if self.is_known(link) {
+                    result["suppressions"] = json!([{ "kind": "external", "justification": "listed as known broken" }]);
+                }
+                results.push(result);
+            }
+        }
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "crawly",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": [{
+                            "id": RULE_ID,
+                            "shortDescription": { "text": "Links should lead to a page that responds" },
+                            "defaultConfiguration": { "level": level },
+                        }],
+                    },
+                },
+                "results": results,
+            }],
+        })
+    }
+}
+
+/// The pages linking to the link, one per line.
+fn referrers(link: &CheckedLink) -> String {
+    link.referrers.iter()
+        .map(|referrer| format!("linked from {} as {:?}\n", referrer.page, referrer.text))
+        .collect()
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::check::{CheckedLink, KnownBroken, LinkReport, ReportFormat, Referrer, Severity};
+    use crate::client::CrawlClientError;
+
+    fn links() -> Vec<CheckedLink> {
+        let referrer = |text: &str| vec![Referrer { page: "https://domain.com/".to_string(), text: text.to_string() }];
+        vec![
+            CheckedLink { url: "https://domain.com/".to_string(), outcome: Ok(200), referrers: vec![] },
+            CheckedLink { url: "https://domain.com/a&b".to_string(), outcome: Err(CrawlClientError::HttpError(404).into()), referrers: referrer("A & B") },
+            CheckedLink { url: "https://old.com/".to_string(), outcome: Err(CrawlClientError::ConnectionError.into()), referrers: referrer("Old") },
+        ]
+    }
+
+    fn written(format: ReportFormat, severity: Severity) -> String {
+        let links = links();
+        let known = KnownBroken::parse("glob:https://old.com/**").unwrap();
+        let mut output = vec![];
+        LinkReport::new(&links, &known, severity).write(format, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn should_fail_only_on_unknown_broken_links_of_error_severity() {
+        let links = links();
+        let known = KnownBroken::parse("glob:https://old.com/**").unwrap();
+
+        assert!(LinkReport::new(&links, &known, Severity::Error).fails());
+        assert!(!LinkReport::new(&links, &known, Severity::Warning).fails());
+        assert!(!LinkReport::new(&links[2..], &known, Severity::Error).fails());
+        assert_eq!(LinkReport::new(&links, &known, Severity::Error).known().len(), 1)
+    }
+
+    #[test]
+    fn should_write_a_test_case_per_link_in_junit_format() {
+        assert_eq!(written(ReportFormat::Junit, Severity::Error), "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n\
+            <testsuite name=\"crawly check\" tests=\"3\" failures=\"1\" skipped=\"1\">\n  \
+            <testcase classname=\"domain.com\" name=\"https://domain.com/\"/>\n  \
+            <testcase classname=\"domain.com\" name=\"https://domain.com/a&amp;b\">\n    \
+            <failure type=\"http\" message=\"The page responded with status 404\">linked from https://domain.com/ as &quot;A &amp; B&quot;\n</failure>\n  \
+            </testcase>\n  \
+            <testcase classname=\"old.com\" name=\"https://old.com/\">\n    \
+            <skipped message=\"known broken: There was an error connection to the page\"/>\n  \
+            </testcase>\n</testsuite>\n</testsuites>\n");
+        assert!(written(ReportFormat::Junit, Severity::Warning).contains("failures=\"0\""))
+    }
+
+    #[test]
+    fn should_locate_sarif_results_at_the_referring_page() {
+        let sarif: serde_json::Value = serde_json::from_str(&written(ReportFormat::Sarif, Severity::Warning)).unwrap();
+        let results = &sarif["runs"][0]["results"];
+
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(results.as_array().map(|results| results.len()), Some(2));
+        assert_eq!(results[0]["level"], "warning");
+        assert_eq!(results[0]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"], "https://domain.com/");
+        assert_eq!(results[0]["message"]["text"], "Broken link to https://domain.com/a&b (\"A & B\"): The page responded with status 404");
+        assert!(results[0].get("suppressions").is_none());
+        assert_eq!(results[1]["suppressions"][0]["kind"], "external")
+    }
+}
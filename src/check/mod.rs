@@ -0,0 +1,11 @@
+pub use error::{KnownBrokenError, LinkError};
+pub use known_broken::KnownBroken;
+pub use link_checker::LinkChecker;
+pub use link_graph::{CheckedLink, LinkGraph, LinkOutcome, Referrer};
+pub use link_report::{LinkReport, ReportFormat, Severity};
+
+mod error;
+mod known_broken;
+mod link_checker;
+mod link_graph;
+mod link_report;
@@ -0,0 +1,222 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use hyper::Uri;
+use hyper::client::HttpConnector;
+use hyper::client::connect::dns::{GaiResolver, Name};
+use hyper::service::Service;
+use tokio::net::TcpStream;
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+/// Cloud metadata services reachable from inside a VM, on top of the link-local range.
+const METADATA_ADDRESSES: &[IpAddr] = &[
+    IpAddr::V4(Ipv4Addr::new(100, 100, 100, 200)),
+    IpAddr::V6(Ipv6Addr::new(0xfd00, 0x0ec2, 0, 0, 0, 0, 0, 0x0254)),
+];
+
+/// Which addresses the crawler may connect to.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AddressPolicy {
+    #[default]
+    AllowAll,
+    /// Refuses loopback, private, link-local, shared, multicast, unspecified and metadata
+    /// addresses, also when embedded in an IPv6 address, as a guard against links that point
+    /// the crawler at internal services.
+    BlockInternal,
+}
+
+impl AddressPolicy {
+    pub fn blocks(&self, address: IpAddr) -> bool {
+        match self {
+            AddressPolicy::AllowAll => false,
+            AddressPolicy::BlockInternal => is_internal(address),
+        }
+    }
+}
+
+fn is_internal(address: IpAddr) -> bool {
+    if METADATA_ADDRESSES.contains(&address) {
+        return true;
+    }
+    match address {
+        IpAddr::V4(address) => {
+            let [first, second, ..] = address.octets();
+            address.is_loopback()
+                || address.is_private()
+                || address.is_link_local()
+                || address.is_unspecified()
+                || address.is_broadcast()
+                || address.is_multicast()
+                || first == 0
+                || (first == 100 && (64..128).contains(&second))
+        }
+        IpAddr::V6(address) => match embedded_ipv4(address) {
+            Some(embedded) => is_internal(IpAddr::V4(embedded)),
+            None => {
+                let first = address.segments()[0];
+                address.is_loopback()
+                    || address.is_unspecified()
+                    || address.is_multicast()
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80
+            }
+        },
+    }
+}
+
+/// The IPv4 address carried by an IPv4-mapped (`::ffff:a.b.c.d`), IPv4-compatible
+/// (`::a.b.c.d`), NAT64 (`64:ff9b::a.b.c.d`) or 6to4 (`2002:aabb:ccdd::`) address, which
+/// may reach the same host as the IPv4 address itself.
+fn embedded_ipv4(address: Ipv6Addr) -> Option<Ipv4Addr> {
+    let octets = address.octets();
+    match address.segments() {
+        [0x64, 0xff9b, 0, 0, 0, 0, _, _] => Some(Ipv4Addr::new(octets[12], octets[13], octets[14], octets[15])),
+        [0x2002, ..] => Some(Ipv4Addr::new(octets[2], octets[3], octets[4], octets[5])),
+        _ => address.to_ipv4(),
+    }
+}
+
+/// A connection refused by the address policy.
+#[derive(Debug)]
+pub struct BlockedAddress {
+    pub host: String,
+    pub address: IpAddr,
+}
+
+impl Display for BlockedAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.host == self.address.to_string() {
+            write!(f, "{} is an internal address", self.address)
+        } else {
+            write!(f, "{} resolves to {}, an internal address", self.host, self.address)
+        }
+    }
+}
+
+impl Error for BlockedAddress {}
+
+/// Resolves host names and fails when any of their addresses is blocked. The connection
+/// is made to the addresses checked here, so a second lookup returning an internal address
+/// cannot slip past the check.
+#[derive(Clone)]
+pub struct GuardedResolver {
+    resolver: GaiResolver,
+    policy: AddressPolicy,
+}
+
+impl Service<Name> for GuardedResolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.resolver.poll_ready(cx).map_err(|err| err.into())
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let host = name.as_str().to_string();
+        let policy = self.policy;
+        let resolving = self.resolver.call(name);
+        Box::pin(async move {
+            let addresses: Vec<SocketAddr> = resolving.await?.collect();
+            if let Some(blocked) = addresses.iter().find(|address| policy.blocks(address.ip())) {
+                return Err(Box::new(BlockedAddress { host, address: blocked.ip() }) as BoxError);
+            }
+            Ok(addresses.into_iter())
+        })
+    }
+}
+
+/// An `HttpConnector` that checks every address it connects to against the policy, both
+/// for host names, through the `GuardedResolver`, and for IP addresses in the link.
+#[derive(Clone)]
+pub struct GuardedConnector {
+    connector: HttpConnector<GuardedResolver>,
+    policy: AddressPolicy,
+}
+
+impl GuardedConnector {
+    pub fn new(policy: AddressPolicy) -> GuardedConnector {
+        let mut connector = HttpConnector::new_with_resolver(GuardedResolver { resolver: GaiResolver::new(), policy });
+        connector.enforce_http(false);
+        GuardedConnector { connector, policy }
+    }
+}
+
+impl Service<Uri> for GuardedConnector {
+    type Response = TcpStream;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.connector.poll_ready(cx).map_err(|err| err.into())
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let host = uri.host().unwrap_or("").trim_matches(|c| c == '[' || c == ']').to_string();
+        if let Ok(address) = host.parse::<IpAddr>() {
+            if self.policy.blocks(address) {
+                return Box::pin(async move { Err(Box::new(BlockedAddress { host, address }) as BoxError) });
+            }
+        }
+        let connecting = self.connector.call(uri);
+        Box::pin(async move { connecting.await.map_err(|err| err.into()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use crate::client::address_guard::AddressPolicy;
+
+    fn blocked(address: &str) -> bool {
+        AddressPolicy::BlockInternal.blocks(address.parse::<IpAddr>().unwrap())
+    }
+
+    #[test]
+    fn should_block_internal_addresses() {
+        for address in &["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1",
+            "100.100.100.200", "0.0.0.0", "::1", "fe80::1", "fd00:ec2::254", "fc00::1", "::ffff:127.0.0.1"] {
+            assert!(blocked(address), "{} should be blocked", address);
+        }
+    }
+
+    #[test]
+    fn should_block_internal_addresses_in_nat64_addresses() {
+        assert!(blocked("64:ff9b::a9fe:a9fe"));
+        assert!(blocked("64:ff9b::10.0.0.1"));
+        assert!(!blocked("64:ff9b::8.8.8.8"))
+    }
+
+    #[test]
+    fn should_block_internal_addresses_in_ipv4_compatible_addresses() {
+        assert!(blocked("::127.0.0.1"));
+        assert!(blocked("::169.254.169.254"));
+        assert!(!blocked("::8.8.8.8"))
+    }
+
+    #[test]
+    fn should_block_internal_addresses_in_6to4_addresses() {
+        assert!(blocked("2002:a9fe:a9fe::"));
+        assert!(blocked("2002:c0a8:101::1"));
+        assert!(!blocked("2002:808:808::1"))
+    }
+
+    #[test]
+    fn should_allow_public_addresses() {
+        for address in &["93.184.216.34", "8.8.8.8", "172.32.0.1", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(!blocked(address), "{} should be allowed", address);
+        }
+    }
+
+    #[test]
+    fn should_allow_everything_by_default() {
+        assert!(!AddressPolicy::default().blocks("127.0.0.1".parse().unwrap()))
+    }
+}
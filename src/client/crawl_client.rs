@@ -1,48 +1,124 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::Read;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 
-use hyper::{body::Buf, Client, Uri};
+use hyper::{body::Buf, Uri};
+use hyper::header::LOCATION;
 use select::document::Document;
-use select::predicate::Name;
-use crate::client::CrawlClientError;
-use hyper_tls::HttpsConnector;
-use hyper::client::HttpConnector;
+use select::predicate::{Attr, Name};
+use sha2::{Digest, Sha256};
+use url::Url;
+use crate::client::{CrawlClientError, HttpClient};
+use crate::link::Anchor;
+
+const MAX_REDIRECTS: usize = 10;
+const HEADERS_OF_INTEREST: &[&str] = &["content-type", "content-length", "last-modified", "etag", "cache-control"];
+
+/// What a page responded with: where redirects led, the status and headers of interest, the
+/// links on the page, the ids and `<a name>`s that `#fragment` links can point to, its size,
+/// and a hash of its content to tell when it changes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FetchedPage {
+    pub final_url: String,
+    pub status: Option<u16>,
+    pub headers: BTreeMap<String, String>,
+    pub time_to_headers: Option<Duration>,
+    pub anchors: Vec<Anchor>,
+    pub fragments: Vec<String>,
+    pub bytes: usize,
+    pub content_hash: String,
+}
+
+impl FetchedPage {
+    pub fn new(final_url: &str, anchors: Vec<Anchor>, content: &[u8]) -> FetchedPage {
+        let content_hash = Sha256::digest(content).iter().map(|byte| format!("{:02x}", byte)).collect();
+        FetchedPage {
+            final_url: final_url.to_string(),
+            status: None,
+            headers: BTreeMap::new(),
+            time_to_headers: None,
+            anchors,
+            fragments: vec![],
+            bytes: content.len(),
+            content_hash,
+        }
+    }
+}
 
 #[cfg_attr(test, mockall::automock)]
 #[async_trait]
 pub trait CrawlClient: Sync + Send {
-    async fn crawl_and_fetch_links(&self, link: &str) -> Result<Vec<String>, CrawlClientError>;
+    async fn crawl_and_fetch_links(&self, link: &str) -> Result<FetchedPage, CrawlClientError>;
 }
 
 pub struct CrawleyCrawlClient {
-    client: Client<HttpsConnector<HttpConnector>>,
+    client: HttpClient,
 }
 
 impl CrawleyCrawlClient {
-    pub fn new(client: Client<HttpsConnector<HttpConnector>>) -> CrawleyCrawlClient {
+    pub fn new(client: HttpClient) -> CrawleyCrawlClient {
         CrawleyCrawlClient {
             client
         }
     }
+
+    /// Follows redirects from the link, returning the response that is not one and its link.
+    async fn follow_redirects(&self, link: &str) -> Result<(String, hyper::Response<hyper::Body>), CrawlClientError> {
+        let mut current = link.to_string();
+        for _ in 0..=MAX_REDIRECTS {
+            let response = self.client.get(Uri::from_str(&current)?).await?;
+            let location = response.headers().get(LOCATION).and_then(|location| location.to_str().ok());
+            match location {
+                Some(location) if response.status().is_redirection() => {
+                    current = Url::parse(&current)
+                        .and_then(|base| base.join(location))
+                        .map_err(|_| CrawlClientError::InvalidUri)?
+                        .to_string();
+                }
+                _ => return Ok((current, response)),
+            }
+        }
+        Err(CrawlClientError::TooManyRedirects)
+    }
 }
 
 #[async_trait]
 impl CrawlClient for CrawleyCrawlClient {
-    async fn crawl_and_fetch_links(&self, url: &str) -> Result<Vec<String>, CrawlClientError> {
+    async fn crawl_and_fetch_links(&self, url: &str) -> Result<FetchedPage, CrawlClientError> {
         log::info!("Visiting {}", url);
-        let uri = Uri::from_str(url)?;
-        let response = self.client.get(uri).await?;
+        let started = Instant::now();
+        let (final_url, response) = self.follow_redirects(url).await?;
+        let time_to_headers = started.elapsed();
         if !response.status().is_success() {
-            return Err(CrawlClientError::ConnectionError);
+            return Err(CrawlClientError::HttpError(response.status().as_u16()));
         }
+        let status = response.status().as_u16();
+        let headers = HEADERS_OF_INTEREST.iter()
+            .filter_map(|name| response.headers().get(*name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| (name.to_string(), value.to_string())))
+            .collect();
         let body = hyper::body::aggregate(response).await?;
         let mut bytes: Vec<u8> = vec![];
         body.reader().read_to_end(&mut bytes)?;
-        let links = Document::from(String::from_utf8(bytes)?.as_str())
+        let document = Document::from(String::from_utf8(bytes.clone())?.as_str());
+        let links = document
             .select(Name("a"))
-            .filter_map(|anchor| anchor.attr("href").map(|href| href.to_string()))
-            .collect::<Vec<String>>();
-        Ok(links)
+            .filter_map(|anchor| anchor.attr("href").map(|href| Anchor::new(href, &anchor.text())))
+            .collect::<Vec<Anchor>>();
+        let fragments = document.select(Attr("id", ()))
+            .filter_map(|element| element.attr("id"))
+            .chain(document.select(Name("a")).filter_map(|anchor| anchor.attr("name")))
+            .map(|fragment| fragment.to_string())
+            .collect::<BTreeSet<String>>();
+        Ok(FetchedPage {
+            status: Some(status),
+            fragments: fragments.into_iter().collect(),
+            headers,
+            time_to_headers: Some(time_to_headers),
+            ..FetchedPage::new(&final_url, links, &bytes)
+        })
     }
 }
 
@@ -50,7 +126,8 @@ impl CrawlClient for CrawleyCrawlClient {
 mod tests {
     use wiremock::{MockServer, Mock, ResponseTemplate};
     use wiremock::matchers::{method, path};
-    use crate::client::{create_client, CrawlClient, CrawlClientError};
+    use crate::client::{AddressPolicy, create_client, CrawlClient, CrawlClientError, FetchedPage};
+    use crate::link::Anchor;
 
     #[tokio::test]
     async fn should_call_upstream_and_extract_links_on_successful_response() {
@@ -70,20 +147,78 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let client = create_client();
-        let response = client.crawl_and_fetch_links(format!("{}/base/path/page1.html", mock_server.uri()).as_str())
+        let client = create_client(AddressPolicy::AllowAll);
+        let url = format!("{}/base/path/page1.html", mock_server.uri());
+        let response = client.crawl_and_fetch_links(url.as_str())
             .await;
 
         assert!(response.is_ok());
-        assert_eq!(response.unwrap(), vec![
-            "http://domain.com/some_page1.html",
-            "http://domain.com/some_page2.html",
-            "http://domain.com/home.html"
-        ])
+        let page = response.unwrap();
+        assert_eq!(page.status, Some(200));
+        assert!(page.time_to_headers.is_some());
+        assert_eq!(page, FetchedPage {
+            status: page.status,
+            headers: page.headers.clone(),
+            time_to_headers: page.time_to_headers,
+            ..FetchedPage::new(&url, vec![
+                Anchor::new("http://domain.com/some_page1.html", "page 1"),
+                Anchor::new("http://domain.com/some_page2.html", "page 2"),
+                Anchor::new("http://domain.com/home.html", "home")
+            ], page1.as_bytes())
+        })
+    }
+
+    #[tokio::test]
+    async fn should_follow_redirects_and_keep_headers_of_interest() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/old.html"))
+            .respond_with(ResponseTemplate::new(301).insert_header("Location", "/new.html"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/new.html"))
+            .respond_with(ResponseTemplate::new(200)
+                .insert_header("ETag", "\"v1\"")
+                .insert_header("X-Other", "ignored")
+                .set_body_raw("<a href='a.html'>a</a>", "text/html"))
+            .mount(&mock_server)
+            .await;
+
+        let client = create_client(AddressPolicy::AllowAll);
+        let page = client.crawl_and_fetch_links(&format!("{}/old.html", mock_server.uri())).await.unwrap();
+
+        assert_eq!(page.final_url, format!("{}/new.html", mock_server.uri()));
+        assert_eq!(page.headers.get("content-type"), Some(&"text/html".to_string()));
+        assert_eq!(page.headers.get("etag"), Some(&"\"v1\"".to_string()));
+        assert!(!page.headers.contains_key("x-other"));
+        assert_eq!(page.anchors, vec![Anchor::new("a.html", "a")])
     }
 
     #[tokio::test]
-    async fn should_call_upstream_and_throw_error_on_failure() {
+    async fn should_collect_the_ids_and_anchor_names_of_the_page() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200)
+                .set_body_raw("<h2 id='install'>Install</h2><a name='legacy'></a><p id='install'></p><a href='#install'>up</a>", "text/html"))
+            .mount(&mock_server)
+            .await;
+
+        let client = create_client(AddressPolicy::AllowAll);
+        let page = client.crawl_and_fetch_links(&format!("{}/guide", mock_server.uri())).await.unwrap();
+
+        assert_eq!(page.fragments, vec!["install".to_string(), "legacy".to_string()])
+    }
+
+    #[test]
+    fn should_hash_page_content_as_hex_sha256() {
+        let page = FetchedPage::new("https://domain.com/", vec![], b"abc");
+
+        assert_eq!(page.content_hash, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+    }
+
+    #[tokio::test]
+    async fn should_call_upstream_and_return_the_status_on_failure() {
         let mock_server = MockServer::start().await;
         Mock::given(method("GET"))
             .and(path("/base/path/page1.html"))
@@ -91,11 +226,29 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let client = create_client();
+        let client = create_client(AddressPolicy::AllowAll);
         let response = client.crawl_and_fetch_links(format!("{}/base/path/page1.html", mock_server.uri()).as_str())
             .await;
 
         assert!(response.is_err());
-        assert_eq!(response.err(), Some(CrawlClientError::ConnectionError))
+        assert_eq!(response.err(), Some(CrawlClientError::HttpError(500)))
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn should_refuse_to_connect_to_internal_addresses_when_blocked() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+        let port = mock_server.address().port();
+
+        let client = create_client(AddressPolicy::BlockInternal);
+        let by_address = client.crawl_and_fetch_links(&format!("{}/page1.html", mock_server.uri())).await;
+        let by_name = client.crawl_and_fetch_links(&format!("http://localhost:{}/page1.html", port)).await;
+
+        assert_eq!(by_address.err(), Some(CrawlClientError::BlockedAddress("127.0.0.1 is an internal address".to_string())));
+        assert!(matches!(by_name.err(), Some(CrawlClientError::BlockedAddress(reason)) if reason.starts_with("localhost resolves to")))
+    }
+}
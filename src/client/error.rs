@@ -3,23 +3,49 @@ use std::error::Error;
 use hyper::http::uri::InvalidUri;
 use std::string::FromUtf8Error;
 
-#[derive(Debug, Eq, PartialEq)]
+use crate::client::address_guard::BlockedAddress;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum CrawlClientError {
     InvalidUri,
     ConnectionError,
     IOError,
-    EncodingError
+    EncodingError,
+    BlockedAddress(String),
+    HttpError(u16),
+    TooManyRedirects,
+}
+
+impl CrawlClientError {
+    /// A short name for the kind of error, the same for every error of the kind.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CrawlClientError::InvalidUri => "invalid-uri",
+            CrawlClientError::ConnectionError => "connection",
+            CrawlClientError::IOError => "io",
+            CrawlClientError::EncodingError => "encoding",
+            CrawlClientError::BlockedAddress(_) => "blocked-address",
+            CrawlClientError::HttpError(_) => "http",
+            CrawlClientError::TooManyRedirects => "too-many-redirects",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            CrawlClientError::InvalidUri => "Invalid link or url".to_string(),
+            CrawlClientError::ConnectionError => "There was an error connection to the page".to_string(),
+            CrawlClientError::IOError => "There was an error sending or receiving data".to_string(),
+            CrawlClientError::EncodingError => "There was an error parsing encoded data".to_string(),
+            CrawlClientError::BlockedAddress(reason) => format!("Refused to connect: {}", reason),
+            CrawlClientError::HttpError(status) => format!("The page responded with status {}", status),
+            CrawlClientError::TooManyRedirects => "The page redirected too many times".to_string(),
+        }
+    }
 }
 
 impl Display for CrawlClientError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let display_string = match self {
-            CrawlClientError::InvalidUri => "Invalid link or url",
-            CrawlClientError::ConnectionError => "There was an error connection to the page",
-            CrawlClientError::IOError => "There was an error sending or receiving data",
-            CrawlClientError::EncodingError => "There was an error parsing encoded data",
-        };
-        writeln!(f, "{:?}", display_string)
+        writeln!(f, "{:?}", self.message())
     }
 }
 
@@ -32,7 +58,14 @@ impl From<InvalidUri> for CrawlClientError {
 }
 
 impl From<hyper::Error> for CrawlClientError {
-    fn from(_: hyper::Error) -> Self {
+    fn from(err: hyper::Error) -> Self {
+        let mut source = err.source();
+        while let Some(cause) = source {
+            if let Some(blocked) = cause.downcast_ref::<BlockedAddress>() {
+                return CrawlClientError::BlockedAddress(blocked.to_string());
+            }
+            source = cause.source();
+        }
         CrawlClientError::ConnectionError
     }
 }
@@ -1,20 +1,28 @@
 use hyper::Body;
-use hyper::client::{Client, HttpConnector};
+use hyper::client::Client;
 use hyper_tls::HttpsConnector;
 
-pub use crawl_client::CrawlClient;
+pub use address_guard::AddressPolicy;
+pub use crawl_client::{CrawlClient, FetchedPage};
 pub use error::CrawlClientError;
 
+use crate::client::address_guard::GuardedConnector;
 use crate::client::crawl_client::CrawleyCrawlClient;
 
+mod address_guard;
 mod crawl_client;
 mod error;
 
+pub type HttpClient = Client<HttpsConnector<GuardedConnector>>;
+
 #[cfg(test)]
 pub use crate::client::crawl_client::MockCrawlClient;
 
-pub fn create_client() -> CrawleyCrawlClient {
-    let client = Client::builder()
-        .build::<HttpsConnector<HttpConnector>, Body>(HttpsConnector::new());
-    CrawleyCrawlClient::new(client)
+pub fn create_http_client(policy: AddressPolicy) -> HttpClient {
+    Client::builder()
+        .build::<HttpsConnector<GuardedConnector>, Body>(HttpsConnector::new_with_connector(GuardedConnector::new(policy)))
+}
+
+pub fn create_client(policy: AddressPolicy) -> CrawleyCrawlClient {
+    CrawleyCrawlClient::new(create_http_client(policy))
 }
\ No newline at end of file
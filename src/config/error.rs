@@ -0,0 +1,26 @@
+use std::fmt::{Display, Formatter};
+use std::error::Error;
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum ConfigError {
+    IOError(String),
+    ParseError(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> std::fmt::Result {
+        let display_string = match self {
+            ConfigError::IOError(path) => format!("Could not read config file {}", path),
+            ConfigError::ParseError(reason) => format!("Invalid config file: {}", reason),
+        };
+        write!(fmt, "{}", display_string)
+    }
+}
+
+impl Error for ConfigError {}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(error: toml::de::Error) -> Self {
+        ConfigError::ParseError(error.to_string())
+    }
+}
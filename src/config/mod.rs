@@ -0,0 +1,117 @@
+use std::fs;
+
+use serde::Deserialize;
+
+use crate::queue::{ResourceTypePolicy, SitePolicy, TrapThresholds};
+
+pub use error::ConfigError;
+
+mod error;
+
+/// Settings read from the file given with `--config`. Command line options are added on top of these.
+#[derive(Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrawlConfig {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub site: SitePolicy,
+    pub restrict_to_seed_path: bool,
+    pub path_prefixes: Vec<String>,
+    pub resources: ResourceTypePolicy,
+    pub traps: TrapThresholds,
+}
+
+impl CrawlConfig {
+    pub fn load(path: &str) -> Result<CrawlConfig, ConfigError> {
+        let contents = fs::read_to_string(path).map_err(|_| ConfigError::IOError(path.to_string()))?;
+        CrawlConfig::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> Result<CrawlConfig, ConfigError> {
+        Ok(toml::from_str(contents)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{CrawlConfig, ConfigError};
+    use crate::queue::{ResourceTypePolicy, SitePolicy, TrapThresholds};
+
+    #[test]
+    fn should_parse_include_and_exclude_patterns() {
+        let config = CrawlConfig::parse(r#"
+            include = ["/docs/**"]
+            exclude = ["regex:\\?page=\\d+"]
+        "#);
+
+        assert_eq!(config, Ok(CrawlConfig {
+            include: vec!["/docs/**".to_string()],
+            exclude: vec!["regex:\\?page=\\d+".to_string()],
+            ..CrawlConfig::default()
+        }))
+    }
+
+    #[test]
+    fn should_parse_site_policy() {
+        let config = CrawlConfig::parse(r#"
+            [site]
+            include_subdomains = true
+            match_port = true
+        "#);
+
+        assert_eq!(config.unwrap().site, SitePolicy {
+            include_subdomains: true,
+            match_port: true,
+            ..SitePolicy::default()
+        })
+    }
+
+    #[test]
+    fn should_parse_path_prefixes() {
+        let config = CrawlConfig::parse(r#"
+            restrict_to_seed_path = true
+            path_prefixes = ["/docs/v2/"]
+        "#).unwrap();
+
+        assert!(config.restrict_to_seed_path);
+        assert_eq!(config.path_prefixes, vec!["/docs/v2/"])
+    }
+
+    #[test]
+    fn should_parse_resource_types() {
+        let config = CrawlConfig::parse(r#"
+            [resources]
+            allow = ["pdf"]
+            deny = ["application/json"]
+        "#).unwrap();
+
+        assert_eq!(config.resources, ResourceTypePolicy {
+            allow: vec!["pdf".to_string()],
+            deny: vec!["application/json".to_string()],
+        })
+    }
+
+    #[test]
+    fn should_parse_trap_thresholds_keeping_defaults_for_missing_ones() {
+        let config = CrawlConfig::parse(r#"
+            [traps]
+            max_path_depth = 8
+        "#).unwrap();
+
+        assert_eq!(config.traps, TrapThresholds { max_path_depth: 8, ..TrapThresholds::default() })
+    }
+
+    #[test]
+    fn should_default_missing_settings() {
+        let config = CrawlConfig::parse("");
+
+        assert_eq!(config, Ok(CrawlConfig::default()))
+    }
+
+    #[test]
+    fn should_fail_on_unknown_settings() {
+        let config = CrawlConfig::parse("includes = []");
+
+        assert!(matches!(config, Err(ConfigError::ParseError(_))))
+    }
+}
@@ -0,0 +1,294 @@
+use std::sync::Arc;
+
+use futures::{Stream, StreamExt};
+
+use crate::client::{self, AddressPolicy, CrawlClient};
+use crate::crawly::Crawly;
+use crate::event::CrawlEvent;
+use crate::page::{CrawlResults, PageResult};
+use crate::queue::{self, Checkpoint, CrawlQueue, CrawlStrategy, QueueAdditionDecider, QueueConfigurationError, QueueSettings, ScopeSettings};
+use crate::recrawl::{PageHistory, RecrawlClient};
+use crate::result_publisher::{ResultPublisher, TeePublisher, TokioResultPublisher};
+use crate::service::{CrawleyScrapeService, ScraperError};
+
+/// Sets up a crawl: its seeds, which links it follows, how it connects, how far it goes and
+/// who else hears about the pages it visits. Anything not set has the command line's default.
+#[derive(Default)]
+pub struct CrawlerBuilder {
+    seeds: Vec<String>,
+    scope: ScopeSettings,
+    settings: QueueSettings,
+    publishers: Vec<Box<dyn ResultPublisher<CrawlEvent, ScraperError>>>,
+    history: Option<Arc<PageHistory>>,
+    checkpoint: Option<Checkpoint>,
+}
+
+impl CrawlerBuilder {
+    pub fn new() -> CrawlerBuilder {
+        CrawlerBuilder::default()
+    }
+
+    pub fn seeds(mut self, seeds: Vec<String>) -> CrawlerBuilder {
+        self.seeds.extend(seeds);
+        self
+    }
+
+    pub fn seed(self, seed: &str) -> CrawlerBuilder {
+        self.seeds(vec![seed.to_string()])
+    }
+
+    /// Which links are followed. Replaces the deciders added before.
+    pub fn scope(mut self, scope: ScopeSettings) -> CrawlerBuilder {
+        self.scope = scope;
+        self
+    }
+
+    /// Adds a decider run after the ones of the scope.
+    pub fn decider(mut self, decider: Box<dyn QueueAdditionDecider>) -> CrawlerBuilder {
+        self.scope.deciders.push(decider);
+        self
+    }
+
+    /// Which addresses the crawler connects to, for pages and for content type checks.
+    pub fn address_policy(mut self, policy: AddressPolicy) -> CrawlerBuilder {
+        self.scope.address_policy = policy;
+        self
+    }
+
+    /// How the crawl is ordered, limited, checkpointed and remembers its pages. Replaces the
+    /// limits set before.
+    pub fn queue_settings(mut self, settings: QueueSettings) -> CrawlerBuilder {
+        self.settings = settings;
+        self
+    }
+
+    pub fn strategy(mut self, strategy: CrawlStrategy) -> CrawlerBuilder {
+        self.settings.strategy = strategy;
+        self
+    }
+
+    pub fn max_pages(mut self, max_pages: usize) -> CrawlerBuilder {
+        self.settings.max_pages = Some(max_pages);
+        self
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> CrawlerBuilder {
+        self.settings.batch_size = Some(concurrency);
+        self
+    }
+
+    /// Adds a publisher told about every event of the crawl, along with the crawl's stream.
+    pub fn publisher(mut self, publisher: Box<dyn ResultPublisher<CrawlEvent, ScraperError>>) -> CrawlerBuilder {
+        self.publishers.push(publisher);
+        self
+    }
+
+    /// Compares the pages with the previous crawl, skipping the ones not due for a visit.
+    pub fn recrawl(mut self, history: Arc<PageHistory>) -> CrawlerBuilder {
+        self.history = Some(history);
+        self
+    }
+
+    /// Continues a checkpointed crawl. Seeds default to the checkpointed ones.
+    pub fn resume(mut self, checkpoint: Checkpoint) -> CrawlerBuilder {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+
+    pub fn build(self) -> Result<Crawler, QueueConfigurationError> {
+        let seeds = match (&self.checkpoint, self.seeds.is_empty()) {
+            (Some(checkpoint), true) => checkpoint.seeds.clone(),
+            _ => self.seeds,
+        };
+        let address_policy = self.scope.address_policy;
+        let queue = queue::create_queue(&seeds, self.scope, self.settings)?;
+        if let Some(checkpoint) = self.checkpoint {
+            queue.restore(checkpoint);
+        }
+        Ok(Crawler { seeds, queue, address_policy, publishers: self.publishers, history: self.history })
+    }
+}
+
+/// A crawl ready to start.
+pub struct Crawler {
+    seeds: Vec<String>,
+    queue: CrawlQueue,
+    address_policy: AddressPolicy,
+    publishers: Vec<Box<dyn ResultPublisher<CrawlEvent, ScraperError>>>,
+    history: Option<Arc<PageHistory>>,
+}
+
+impl Crawler {
+    /// Starts the crawl when the stream is first polled, and yields each page as soon as it is
+    /// visited. The crawl runs as the stream is polled, and stops when it is dropped. The
+    /// stream ends with the crawl.
+    pub fn crawl(self) -> impl Stream<Item = PageResult> {
+        self.events().filter_map(|event| futures::future::ready(event.into_page()))
+    }
+
+    /// Runs the whole crawl, keeping the pages that failed apart from the others, and the
+    /// error the crawl stopped on, if any.
+    pub async fn run(self) -> CrawlResults {
+        self.events()
+            .fold(CrawlResults::default(), |mut results, event| {
+                match event {
+                    CrawlEvent::CrawlFailed { reason } => results.error = Some(reason),
+                    event => if let Some(page) = event.into_page() {
+                        results.add(page);
+                    },
+                }
+                futures::future::ready(results)
+            })
+            .await
+    }
+
+    /// Like `crawl`, yielding every event of the crawl instead of only the pages. A crawl
+    /// that stops on an error ends with a `CrawlFailed` event.
+    pub fn events(self) -> impl Stream<Item = CrawlEvent> {
+        let (tx, rx) = tokio::sync::mpsc::channel::<CrawlEvent>(2048);
+        let mut publishers: Vec<Box<dyn ResultPublisher<CrawlEvent, ScraperError>>> = vec![Box::new(TokioResultPublisher::new(tx))];
+        publishers.extend(self.publishers);
+        let publisher = Arc::new(TeePublisher::new(publishers));
+        let client = client::create_client(self.address_policy);
+        let Crawler { seeds, queue, history, .. } = self;
+        let crawling = async move {
+            let crawled = match history {
+                Some(history) => crawl(RecrawlClient::new(client, history), queue, seeds, publisher).await,
+                None => crawl(client, queue, seeds, publisher).await,
+            };
+            if let Err(err) = crawled {
+                log::error!("The crawl stopped: {}", err);
+            }
+        };
+        let events = futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|event| (event, rx))
+        });
+        futures::stream::select(events, futures::stream::once(crawling).filter_map(|_| futures::future::ready(None)))
+    }
+}
+
+/// Crawls with the client, publishing what happens. The publisher is dropped with the crawl,
+/// which ends the stream.
+async fn crawl<C: CrawlClient>(client: C, queue: CrawlQueue, seeds: Vec<String>, publisher: Arc<TeePublisher<CrawlEvent, ScraperError>>) -> Result<(), Box<dyn std::error::Error>> {
+    let crawly = Crawly::new(CrawleyScrapeService::new(client, queue, publisher.clone()), publisher);
+    crawly.start_crawling(seeds).await
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+    use wiremock::matchers::{method, path};
+
+    use crate::CrawlerBuilder;
+    use crate::event::CrawlEvent;
+    use crate::queue::ScopeSettings;
+    use crate::result_publisher::MockResultPublisher;
+    use crate::service::ScraperError;
+
+    #[tokio::test]
+    async fn should_stream_the_pages_of_the_crawl_and_tell_the_publishers() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("<a href='/page.html'>page</a>", "text/html"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/page.html"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("no links", "text/html"))
+            .mount(&server)
+            .await;
+        let mut publisher = MockResultPublisher::<CrawlEvent, ScraperError>::new();
+        publisher
+            .expect_notify()
+            .withf(|event| matches!(event, CrawlEvent::PageFetched(_)))
+            .times(2)
+            .returning(|event| Box::pin(futures::future::ok(event)));
+        publisher
+            .expect_notify()
+            .returning(|event| Box::pin(futures::future::ok(event)));
+
+        let crawler = CrawlerBuilder::new()
+            .seed(&format!("{}/", server.uri()))
+            .publisher(Box::new(publisher))
+            .build()
+            .unwrap();
+        let mut urls: Vec<String> = crawler.crawl().map(|result| result.url).collect().await;
+        urls.sort();
+
+        assert_eq!(urls, vec![format!("{}/", server.uri()), format!("{}/page.html", server.uri())])
+    }
+
+    #[tokio::test]
+    async fn should_stop_after_the_page_limit() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("<a href='/next.html'>next</a>", "text/html"))
+            .mount(&server)
+            .await;
+
+        let crawler = CrawlerBuilder::new()
+            .seed(&format!("{}/", server.uri()))
+            .max_pages(1)
+            .build()
+            .unwrap();
+        let results = crawler.run().await;
+
+        assert_eq!(results.successes.len(), 1)
+    }
+
+    #[tokio::test]
+    async fn should_crawl_from_a_seed_outside_the_path_prefixes() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("<a href='/docs/intro.html'>docs</a><a href='/blog/post.html'>blog</a>", "text/html"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("no links", "text/html"))
+            .mount(&server)
+            .await;
+
+        let crawler = CrawlerBuilder::new()
+            .seed(&format!("{}/", server.uri()))
+            .scope(ScopeSettings { path_prefixes: vec!["/docs/".to_string()], ..ScopeSettings::default() })
+            .build()
+            .unwrap();
+        let mut urls: Vec<String> = crawler.crawl().map(|result| result.url).collect().await;
+        urls.sort();
+
+        assert_eq!(urls, vec![format!("{}/", server.uri()), format!("{}/docs/intro.html", server.uri())])
+    }
+
+    /// Links every page to the next one, for crawls that do not end on their own.
+    struct EndlessPages;
+
+    impl Respond for EndlessPages {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let page: u64 = request.url.path().trim_start_matches('/').parse().unwrap_or(0);
+            ResponseTemplate::new(200).set_body_raw(format!("<a href='/{}'>next</a>", page + 1), "text/html")
+        }
+    }
+
+    #[tokio::test]
+    async fn should_yield_pages_before_the_crawl_ends() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(EndlessPages)
+            .mount(&server)
+            .await;
+
+        let crawler = CrawlerBuilder::new()
+            .seed(&format!("{}/", server.uri()))
+            .concurrency(1)
+            .max_pages(1000)
+            .build()
+            .unwrap();
+        let first = Box::pin(crawler.crawl()).next().await;
+
+        assert_eq!(first.map(|result| result.url), Some(format!("{}/", server.uri())));
+        assert!(server.received_requests().await.unwrap().len() < 1000)
+    }
+}
@@ -1,61 +1,178 @@
-use tokio::sync::mpsc::{Receiver};
+use std::time::Instant;
 
-use crate::service::ScrapeService;
+use crate::event::CrawlEvent;
+use crate::page::PageResult;
+use crate::result_publisher::ResultPublisher;
+use crate::service::{ScrapeService, ScraperError};
 
-pub struct Crawly<Scraper: ScrapeService> {
+#[derive(Default)]
+struct Counts {
+    pages: usize,
+    failures: usize,
+}
+
+impl Counts {
+    fn add(&mut self, results: &[PageResult]) {
+        self.pages += results.len();
+        self.failures += results.iter().filter(|result| result.error.is_some()).count();
+    }
+}
+
+pub struct Crawly<Scraper: ScrapeService, P: ResultPublisher<CrawlEvent, ScraperError>> {
     scraper: Scraper,
+    publisher: P,
 }
 
-impl<Scraper: ScrapeService + Sync> Crawly<Scraper> {
-    pub fn new(scraper: Scraper) -> Crawly<Scraper> {
+impl<Scraper: ScrapeService + Sync, P: ResultPublisher<CrawlEvent, ScraperError>> Crawly<Scraper, P> {
+    pub fn new(scraper: Scraper, publisher: P) -> Crawly<Scraper, P> {
         Crawly {
             scraper,
+            publisher,
         }
     }
 
-    pub async fn start_crawling(&self, start_url: &str, rx: &mut Receiver<Vec<String>>) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        let _ = self.scraper.scrape_links(vec![start_url.to_string()]).await;
-        while let Some(res) = rx.recv().await {
-            let _ = self.scraper.scrape_links(res).await?;
-            if !self.scraper.has_more_items_to_scrape() {
-                rx.close();
+    /// Crawls from the seeds until the queue runs out. Pages are published by the scraper as
+    /// they are visited, and the start and end of the crawl here, or the error it stopped on.
+    pub async fn start_crawling(&self, seeds: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+        let started = Instant::now();
+        self.notify(CrawlEvent::CrawlStarted { seeds: seeds.clone() }).await;
+        let counts = match self.scrape(seeds).await {
+            Ok(counts) => counts,
+            Err(err) => {
+                self.notify(CrawlEvent::CrawlFailed { reason: err.to_string() }).await;
+                return Err(err.into());
             }
+        };
+        self.notify(CrawlEvent::CrawlFinished {
+            pages: counts.pages,
+            failures: counts.failures,
+            duration: started.elapsed(),
+        }).await;
+        Ok(())
+    }
+
+    async fn scrape(&self, seeds: Vec<String>) -> Result<Counts, ScraperError> {
+        let mut counts = Counts::default();
+        counts.add(&self.scraper.scrape_links(seeds).await?);
+        while self.scraper.has_more_items_to_scrape() {
+            counts.add(&self.scraper.scrape_links(vec![]).await?);
+        }
+        Ok(counts)
+    }
+
+    async fn notify(&self, event: CrawlEvent) {
+        if let Err(err) = self.publisher.notify(event).await {
+            log::warn!("Unable to publish a crawl event: {}", err);
         }
-        Ok(self.scraper.result().to_vec())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use tokio::sync::mpsc::channel;
-
     use crate::crawly::Crawly;
-    use crate::service::MockScrapeService;
+    use crate::event::CrawlEvent;
+    use crate::result_publisher::MockResultPublisher;
+    use crate::service::{MockScrapeService, ScraperError};
+
+    fn publisher() -> MockResultPublisher<CrawlEvent, ScraperError> {
+        let mut publisher = MockResultPublisher::<CrawlEvent, ScraperError>::new();
+        publisher
+            .expect_notify()
+            .returning(|event| Box::pin(futures::future::ok(event)));
+        publisher
+    }
 
     #[tokio::test]
-    async fn should_call_service_every_time_there_is_data_in_the_channel() {
-        let (tx, mut rx) = channel::<Vec<String>>(2048);
+    async fn should_scrape_the_seeds_and_stop_when_nothing_is_left() {
         let mut service = MockScrapeService::new();
         service
             .expect_scrape_links()
-            .times(5)
+            .withf(|links| links == &["https://test.com/start.html".to_string()])
+            .times(1)
             .returning(|_| Ok(vec![]));
         service
             .expect_has_more_items_to_scrape()
             .returning(|| false);
+
+        let crawly = Crawly::new(service, publisher());
+
+        let result = crawly.start_crawling(vec!["https://test.com/start.html".to_string()]).await;
+
+        assert!(result.is_ok())
+    }
+
+    #[tokio::test]
+    async fn should_keep_scraping_while_service_has_items() {
+        let mut service = MockScrapeService::new();
+        service
+            .expect_scrape_links()
+            .times(2)
+            .returning(|_| Ok(vec![]));
+        let mut remaining = 2;
         service
-            .expect_result()
-            .returning(|| vec![]);
+            .expect_has_more_items_to_scrape()
+            .returning(move || {
+                remaining -= 1;
+                remaining > 0
+            });
+
+        let crawly = Crawly::new(service, publisher());
+
+        let result = crawly.start_crawling(vec!["https://test.com/start.html".to_string()]).await;
+
+        assert!(result.is_ok())
+    }
+
+    #[tokio::test]
+    async fn should_publish_the_start_and_the_end_of_the_crawl() {
+        let mut service = MockScrapeService::new();
+        service
+            .expect_scrape_links()
+            .returning(|_| Ok(vec![]));
+        service
+            .expect_has_more_items_to_scrape()
+            .returning(|| false);
+        let mut publisher = MockResultPublisher::<CrawlEvent, ScraperError>::new();
+        publisher
+            .expect_notify()
+            .withf(|event| event == &CrawlEvent::CrawlStarted { seeds: vec!["https://test.com/".to_string()] })
+            .times(1)
+            .returning(|event| Box::pin(futures::future::ok(event)));
+        publisher
+            .expect_notify()
+            .withf(|event| matches!(event, CrawlEvent::CrawlFinished { pages: 0, failures: 0, .. }))
+            .times(1)
+            .returning(|event| Box::pin(futures::future::ok(event)));
 
-        let crawly = Crawly::new(service);
+        let crawly = Crawly::new(service, publisher);
+
+        let result = crawly.start_crawling(vec!["https://test.com/".to_string()]).await;
+
+        assert!(result.is_ok())
+    }
+
+    #[tokio::test]
+    async fn should_publish_the_error_the_crawl_stopped_on() {
+        let mut service = MockScrapeService::new();
+        service
+            .expect_scrape_links()
+            .returning(|_| Err(ScraperError::InvalidUrl(": not a url".to_string())));
+        let mut publisher = MockResultPublisher::<CrawlEvent, ScraperError>::new();
+        publisher
+            .expect_notify()
+            .withf(|event| matches!(event, CrawlEvent::CrawlStarted { .. }))
+            .times(1)
+            .returning(|event| Box::pin(futures::future::ok(event)));
+        publisher
+            .expect_notify()
+            .withf(|event| event == &CrawlEvent::CrawlFailed { reason: "Invalid link or url: not a url".to_string() })
+            .times(1)
+            .returning(|event| Box::pin(futures::future::ok(event)));
 
-        let _ = tx.send(vec!["page1".to_string()]).await;
-        let _ = tx.send(vec!["page2".to_string()]).await;
-        let _ = tx.send(vec!["page3".to_string()]).await;
-        let _ = tx.send(vec!["page4".to_string()]).await;
+        let crawly = Crawly::new(service, publisher);
 
-        let result = crawly.start_crawling("https://test.com/start.html", &mut rx).await;
+        let result = crawly.start_crawling(vec!["not a url".to_string()]).await;
 
-        assert_eq!(result.unwrap(), Vec::<String>::new())
+        assert!(result.is_err())
     }
 }
@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use crate::page::PageResult;
+use crate::queue::FrontierEntry;
+
+/// What happens during a crawl, in the order it happens for each link.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CrawlEvent {
+    CrawlStarted { seeds: Vec<String> },
+    UrlQueued(FrontierEntry),
+    /// A link turned down by a decider. Links found again after being seen are left out.
+    UrlRejected { entry: FrontierEntry, decider: String, reason: String },
+    FetchStarted(FrontierEntry),
+    PageFetched(PageResult),
+    FetchFailed(PageResult),
+    CrawlFinished { pages: usize, failures: usize, duration: Duration },
+    /// The crawl stopped on an error before it was finished. No event follows it.
+    CrawlFailed { reason: String },
+}
+
+impl CrawlEvent {
+    /// The page of a fetch, whether it succeeded or failed.
+    pub fn into_page(self) -> Option<PageResult> {
+        match self {
+            CrawlEvent::PageFetched(page) | CrawlEvent::FetchFailed(page) => Some(page),
+            _ => None,
+        }
+    }
+
+    /// Tells a fetched page from a failed one.
+    pub fn fetched(page: PageResult) -> CrawlEvent {
+        if page.error.is_none() {
+            CrawlEvent::PageFetched(page)
+        } else {
+            CrawlEvent::FetchFailed(page)
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use serde::Serialize;
+use url::Url;
+
+use crate::event::CrawlEvent;
+
+/// How many of the slowest pages the summary keeps.
+const SLOWEST_PAGES: usize = 10;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SlowPage {
+    pub url: String,
+    pub millis: u64,
+}
+
+/// Totals of a crawl, gathered from its events: pages and bytes fetched, how long it took,
+/// the status codes and kinds of errors, the slowest pages, the pages of each host, and how
+/// often each decider turned a link down. A link found on several pages is turned down each
+/// time, so that the summary does not have to keep the links.
+#[derive(Debug, Default, Serialize)]
+pub struct CrawlSummary {
+    pub pages: usize,
+    pub bytes: usize,
+    pub seconds: f64,
+    pub pages_per_second: f64,
+    pub statuses: BTreeMap<u16, usize>,
+    pub errors: BTreeMap<&'static str, usize>,
+    pub slowest: Vec<SlowPage>,
+    pub hosts: BTreeMap<String, usize>,
+    pub rejected: BTreeMap<String, usize>,
+}
+
+impl CrawlSummary {
+    pub fn record(&mut self, event: &CrawlEvent) {
+        match event {
+            CrawlEvent::PageFetched(page) | CrawlEvent::FetchFailed(page) => {
+                self.pages += 1;
+                self.bytes += page.bytes;
+                if let Some(status) = page.status {
+                    *self.statuses.entry(status).or_insert(0) += 1;
+                }
+                if let Some(error) = &page.error {
+                    *self.errors.entry(error.kind()).or_insert(0) += 1;
+                }
+                if let Some(host) = host(&page.url) {
+                    *self.hosts.entry(host).or_insert(0) += 1;
+                }
+                self.slowest.push(SlowPage { url: page.url.clone(), millis: page.timings.total.as_millis() as u64 });
+                self.slowest.sort_by_key(|page| std::cmp::Reverse(page.millis));
+                self.slowest.truncate(SLOWEST_PAGES);
+            }
+            CrawlEvent::UrlRejected { decider, .. } => {
+                *self.rejected.entry(decider.clone()).or_insert(0) += 1;
+            }
+            CrawlEvent::CrawlFinished { duration, .. } => {
+                self.seconds = duration.as_secs_f64();
+                if self.seconds > 0.0 {
+                    self.pages_per_second = self.pages as f64 / self.seconds;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Display for CrawlSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Crawled {} pages, {} bytes, in {:.2}s ({:.1} pages/s)", self.pages, self.bytes, self.seconds, self.pages_per_second)?;
+        writeln!(f, "Statuses: {}", counts(self.statuses.iter().map(|(status, count)| (status.to_string(), *count))))?;
+        writeln!(f, "Errors: {}", counts(self.errors.iter().map(|(kind, count)| (kind.to_string(), *count))))?;
+        writeln!(f, "Hosts: {}", counts(self.hosts.iter().map(|(host, count)| (host.clone(), *count))))?;
+        writeln!(f, "Rejected: {}", counts(self.rejected.iter().map(|(decider, count)| (decider.clone(), *count))))?;
+        writeln!(f, "Slowest pages:")?;
+        for page in &self.slowest {
+            writeln!(f, "  {} ms\t{}", page.millis, page.url)?;
+        }
+        Ok(())
+    }
+}
+
+/// The host of the link, with its port when it is not the scheme's default.
+fn host(link: &str) -> Option<String> {
+    let url = Url::parse(link).ok()?;
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    })
+}
+
+fn counts(counts: impl Iterator<Item = (String, usize)>) -> String {
+    let counts: Vec<String> = counts.map(|(name, count)| format!("{} {}", name, count)).collect();
+    if counts.is_empty() {
+        "none".to_string()
+    } else {
+        counts.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::client::CrawlClientError;
+    use crate::event::{CrawlEvent, CrawlSummary};
+    use crate::page::PageResult;
+    use crate::queue::FrontierEntry;
+
+    fn page(url: &str, status: u16, bytes: usize, millis: u64) -> PageResult {
+        let mut page = PageResult::new(&FrontierEntry::seed(url));
+        page.status = Some(status);
+        page.bytes = bytes;
+        page.timings.total = Duration::from_millis(millis);
+        page
+    }
+
+    fn rejected(url: &str, decider: &str) -> CrawlEvent {
+        CrawlEvent::UrlRejected { entry: FrontierEntry::seed(url), decider: decider.to_string(), reason: String::new() }
+    }
+
+    fn summary() -> CrawlSummary {
+        let mut summary = CrawlSummary::default();
+        [
+            CrawlEvent::PageFetched(page("https://domain.com/", 200, 300, 20)),
+            CrawlEvent::PageFetched(page("https://domain.com/slow", 200, 100, 90)),
+            CrawlEvent::FetchFailed(PageResult { error: Some(CrawlClientError::HttpError(404)), ..page("https://other.com/missing", 404, 0, 5) }),
+            rejected("https://elsewhere.com/", "same-site"),
+            rejected("https://elsewhere.com/", "same-site"),
+            rejected("https://domain.com/file.pdf", "resource-type"),
+            CrawlEvent::CrawlFinished { pages: 3, failures: 1, duration: Duration::from_secs(2) },
+        ].iter().for_each(|event| summary.record(event));
+        summary
+    }
+
+    #[test]
+    fn should_total_the_pages_of_the_crawl() {
+        let summary = summary();
+
+        assert_eq!(summary.pages, 3);
+        assert_eq!(summary.bytes, 400);
+        assert_eq!(summary.pages_per_second, 1.5);
+        assert_eq!(summary.statuses.into_iter().collect::<Vec<(u16, usize)>>(), vec![(200, 2), (404, 1)]);
+        assert_eq!(summary.errors.into_iter().collect::<Vec<(&str, usize)>>(), vec![("http", 1)]);
+        assert_eq!(summary.hosts.into_iter().collect::<Vec<(String, usize)>>(), vec![("domain.com".to_string(), 2), ("other.com".to_string(), 1)]);
+        assert_eq!(summary.slowest[0].url, "https://domain.com/slow");
+    }
+
+    #[test]
+    fn should_count_the_rejections_of_each_decider() {
+        let summary = summary();
+
+        assert_eq!(summary.rejected.into_iter().collect::<Vec<(String, usize)>>(), vec![("resource-type".to_string(), 1), ("same-site".to_string(), 2)])
+    }
+
+    #[test]
+    fn should_serialize_to_json() {
+        let json = serde_json::to_value(summary()).unwrap();
+
+        assert_eq!(json["statuses"]["404"], 1);
+        assert_eq!(json["rejected"]["same-site"], 2)
+    }
+}
@@ -0,0 +1,5 @@
+pub use crawl_event::CrawlEvent;
+pub use crawl_summary::{CrawlSummary, SlowPage};
+
+mod crawl_event;
+mod crawl_summary;
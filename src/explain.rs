@@ -0,0 +1,44 @@
+use crate::link::LinkConstructor;
+use crate::queue::CrawlQueue;
+
+/// Describes what the queue would do with a link found on the first seed, as one tab
+/// separated line: the outcome, the normalized link, the deciding decider and its reason.
+pub fn explain(queue: &CrawlQueue, base: &LinkConstructor, candidate: &str) -> String {
+    let link = match base.construct(candidate) {
+        Ok(link) => link,
+        Err(err) => return format!("invalid\t{}\t-\t{}", candidate, err.to_string().trim_start_matches(": ")),
+    };
+    let decision = queue.decide(&link);
+    let outcome = if decision.accepted { "accepted" } else { "rejected" };
+    format!("{}\t{}\t{}\t{}", outcome, link, decision.decider, decision.reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use crate::explain::explain;
+    use crate::link::LinkConstructor;
+    use crate::queue::{create_queue, QueueSettings, ScopeSettings, UrlPattern};
+
+    fn explained(candidate: &str) -> String {
+        let seeds = vec!["https://example.com/docs/".to_string()];
+        let scope = ScopeSettings {
+            exclude: vec![UrlPattern::try_from("/docs/old/**").unwrap()],
+            ..ScopeSettings::default()
+        };
+        let queue = create_queue(&seeds, scope, QueueSettings::default()).unwrap();
+        explain(&queue, &LinkConstructor::try_from(seeds[0].as_str()).unwrap(), candidate)
+    }
+
+    #[test]
+    fn should_normalize_relative_links_against_the_seed() {
+        assert_eq!(explained("intro.html"), "accepted\thttps://example.com/docs/intro.html\tscope\taccepted by javascript, same-site, exclude, resource-type, trap")
+    }
+
+    #[test]
+    fn should_name_the_decider_rejecting_a_link() {
+        assert_eq!(explained("/docs/old/intro.html"), "rejected\thttps://example.com/docs/old/intro.html\texclude\tmatches /docs/old/**");
+        assert_eq!(explained("https://other.com/"), "rejected\thttps://other.com/\tsame-site\tother.com is not on the same site as any seed")
+    }
+}
@@ -0,0 +1,24 @@
+//! Crawls a site from its seeds, reporting what was found on every page it visits.
+//!
+//! A crawl is set up with a [`CrawlerBuilder`] and runs as a stream of [`PageResult`]s.
+
+#[macro_use]
+extern crate async_trait;
+
+pub use crawler::{Crawler, CrawlerBuilder};
+pub use page::{CrawlResults, Link, PageResult};
+
+pub mod check;
+pub mod client;
+pub mod config;
+pub mod event;
+pub mod explain;
+pub mod link;
+pub mod output;
+pub mod page;
+pub mod queue;
+pub mod recrawl;
+pub mod result_publisher;
+pub mod service;
+mod crawler;
+mod crawly;
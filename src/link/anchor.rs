@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Anchor {
+    pub href: String,
+    pub text: String,
+}
+
+impl Anchor {
+    pub fn new(href: &str, text: &str) -> Anchor {
+        Anchor {
+            href: href.to_string(),
+            text: text.split_whitespace().collect::<Vec<&str>>().join(" "),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::link::Anchor;
+
+    #[test]
+    fn should_collapse_whitespace_in_anchor_text() {
+        let anchor = Anchor::new("/docs.html", "\n   Read the\n   docs  ");
+
+        assert_eq!(anchor.text, "Read the docs");
+        assert_eq!(anchor.href, "/docs.html");
+    }
+}
@@ -43,8 +43,11 @@ impl LinkConstructor {
         if href.starts_with("javascript:") {
             return Err(LinkConstructionError::BadUri)
         }
+        if href == "#" {
+            return Ok(split_fragment(&self.parent).0.to_string())
+        }
         if href.starts_with('#') {
-            return Ok(self.parent.clone())
+            return Ok(format!("{}{}", split_fragment(&self.parent).0, href))
         }
         let href_to_parse = if href.starts_with("//") {
             format!("{}:{}", self.scheme, href)
@@ -64,9 +67,19 @@ impl LinkConstructor {
     }
 }
 
+/// Splits a link into the link without its fragment and the fragment, when there is one.
+pub fn split_fragment(link: &str) -> (&str, Option<&str>) {
+    match link.split_once('#') {
+        Some((base, fragment)) if !fragment.is_empty() => (base, Some(fragment)),
+        Some((base, _)) => (base, None),
+        None => (link, None),
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
-    use crate::link::link_constructor::{LinkConstructor, LinkConstructionError};
+    use crate::link::link_constructor::{split_fragment, LinkConstructor, LinkConstructionError};
     use std::convert::TryFrom;
 
     #[test]
@@ -202,6 +215,21 @@ mod tests {
         let constructed = constructor.construct("#bottom");
 
         assert_eq!(constructed.is_ok(), true);
-        assert_eq!(constructed.unwrap(), "https://crawler.io/base/path1/index.html");
+        assert_eq!(constructed.unwrap(), "https://crawler.io/base/path1/index.html#bottom");
+    }
+
+    #[test]
+    fn should_keep_the_fragment_of_links_to_other_pages_and_drop_empty_ones() {
+        let constructor = LinkConstructor::try_from("https://crawler.io/guide.html").unwrap();
+
+        assert_eq!(constructor.construct("#"), Ok("https://crawler.io/guide.html".to_string()));
+        assert_eq!(constructor.construct("/faq.html#why"), Ok("https://crawler.io/faq.html#why".to_string()));
+    }
+
+    #[test]
+    fn should_split_the_fragment_off_links() {
+        assert_eq!(split_fragment("https://crawler.io/guide.html#installation"), ("https://crawler.io/guide.html", Some("installation")));
+        assert_eq!(split_fragment("https://crawler.io/guide.html#"), ("https://crawler.io/guide.html", None));
+        assert_eq!(split_fragment("https://crawler.io/guide.html"), ("https://crawler.io/guide.html", None));
     }
-}
\ No newline at end of file
+}
@@ -1,5 +1,7 @@
-pub use link_constructor::{LinkConstructor};
+pub use link_constructor::{split_fragment, LinkConstructor};
 pub use error::LinkConstructionError;
+pub use anchor::Anchor;
 
 mod link_constructor;
 mod error;
+mod anchor;
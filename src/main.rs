@@ -1,19 +1,23 @@
-#[macro_use]
-extern crate async_trait;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
 
-use link::LinkConstructor;
-use service::CrawleyScrapeService;
+use clap::{App, AppSettings, Arg, ArgMatches};
+use futures::StreamExt;
 
-use crate::crawly::Crawly;
-use clap::{App, Arg};
-use crate::result_publisher::TokioResultPublisher;
-
-mod queue;
-mod service;
-mod client;
-mod link;
-mod crawly;
-mod result_publisher;
+use crawly::{explain, queue, CrawlerBuilder};
+use crawly::check::{KnownBroken, LinkChecker, LinkGraph, LinkReport, ReportFormat, Severity};
+use crawly::client::{create_http_client, AddressPolicy};
+use crawly::config::CrawlConfig;
+use crawly::link::LinkConstructor;
+use crawly::event::{CrawlEvent, CrawlSummary};
+use crawly::output::{OutputFormat, PageWriter};
+use crawly::page::ErrorSummary;
+use crawly::queue::{BloomFilterSettings, CrawlStrategy, FrontierSegments, SegmentInfo, DiscoveredResources, PatternScorer, QueueSettings, RejectionLog, Checkpoint, CheckpointStore, ResourceTypePolicy, ScopeSettings, ScoreRule, SitePolicy, TrapThresholds, UrlPattern};
+use crawly::recrawl::PageHistory;
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 25)]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -22,22 +26,623 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .version("1.0")
         .author("Senthil V Kumar")
         .about("Crawls the web")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(Arg::new("INPUT")
-            .about("Sets the domain to crawl")
-            .required(true)
+            .about("Sets the domains to crawl")
+            .required_unless_present_any(["seed-file", "resume"])
+            .multiple_values(true)
             .index(1))
+        .arg(Arg::new("seed-file")
+            .global(true)
+            .long("seed-file")
+            .about("Reads additional seed URLs from FILE, one per line. Use - to read from stdin")
+            .value_name("FILE")
+            .takes_value(true))
+        .arg(Arg::new("config")
+            .global(true)
+            .long("config")
+            .about("Reads crawl settings from a TOML FILE. Command line options are added to the ones in the file")
+            .value_name("FILE")
+            .takes_value(true))
+        .arg(Arg::new("include")
+            .global(true)
+            .long("include")
+            .about("Crawls only links matching PATTERN, a glob or a regex prefixed with regex:. Patterns starting with / are matched against the path")
+            .value_name("PATTERN")
+            .takes_value(true)
+            .multiple_occurrences(true))
+        .arg(Arg::new("exclude")
+            .global(true)
+            .long("exclude")
+            .about("Skips links matching PATTERN, even when they also match an --include pattern")
+            .value_name("PATTERN")
+            .takes_value(true)
+            .multiple_occurrences(true))
+        .arg(Arg::new("restrict-to-seed-path")
+            .global(true)
+            .long("restrict-to-seed-path")
+            .about("Crawls only links under the directory of one of the seeds"))
+        .arg(Arg::new("path-prefix")
+            .global(true)
+            .long("path-prefix")
+            .about("Crawls only links under PREFIX, a path or a full URL. Can be given more than once")
+            .value_name("PREFIX")
+            .takes_value(true)
+            .multiple_occurrences(true))
+        .arg(Arg::new("include-subdomains")
+            .global(true)
+            .long("include-subdomains")
+            .about("Crawls every subdomain of the seeds' registrable domains"))
+        .arg(Arg::new("www-equivalent")
+            .global(true)
+            .long("www-equivalent")
+            .about("Treats www.example.com and example.com as the same site"))
+        .arg(Arg::new("match-scheme")
+            .global(true)
+            .long("match-scheme")
+            .about("Treats http and https links as different sites"))
+        .arg(Arg::new("match-port")
+            .global(true)
+            .long("match-port")
+            .about("Treats links on a different port as different sites"))
+        .arg(Arg::new("public-suffix-list")
+            .global(true)
+            .long("public-suffix-list")
+            .about("Reads the public suffix list used by --include-subdomains from FILE")
+            .value_name("FILE")
+            .takes_value(true))
+        .arg(Arg::new("allow-type")
+            .global(true)
+            .long("allow-type")
+            .about("Crawls links to TYPE, an extension like pdf or a MIME type like image/*, even when it is denied by default")
+            .value_name("TYPE")
+            .takes_value(true)
+            .multiple_occurrences(true))
+        .arg(Arg::new("deny-type")
+            .global(true)
+            .long("deny-type")
+            .about("Records links to TYPE, an extension or a MIME type, as resources instead of crawling them")
+            .value_name("TYPE")
+            .takes_value(true)
+            .multiple_occurrences(true))
+        .arg(Arg::new("format")
+            .long("format")
+            .about("Sets how pages are written: plain URLs, a JSON array, one JSON record per line or CSV")
+            .takes_value(true)
+            .possible_values(&["plain", "json", "ndjson", "csv"])
+            .default_value("plain"))
+        .arg(Arg::new("output")
+            .long("output")
+            .about("Writes the pages to FILE instead of stdout")
+            .value_name("FILE")
+            .takes_value(true))
+        .arg(Arg::new("summary")
+            .long("summary")
+            .about("Prints totals, timings, statuses, errors, the slowest pages, pages per host and rejected links per decider to stderr at the end of the crawl, as text or JSON")
+            .value_name("FORMAT")
+            .takes_value(true)
+            .possible_values(&["text", "json"]))
+        .arg(Arg::new("list-resources")
+            .long("list-resources")
+            .about("Prints the links to images, archives and other resources found during the crawl to stderr after the pages"))
+        .arg(Arg::new("max-segment-repeats")
+            .global(true)
+            .long("max-segment-repeats")
+            .about("Skips links repeating a path segment more than N times, as in /a/b/a/b/a/b/. 0 turns the check off")
+            .value_name("N")
+            .takes_value(true))
+        .arg(Arg::new("max-path-depth")
+            .global(true)
+            .long("max-path-depth")
+            .about("Skips links with more than N path segments. 0 turns the check off")
+            .value_name("N")
+            .takes_value(true))
+        .arg(Arg::new("max-url-length")
+            .global(true)
+            .long("max-url-length")
+            .about("Skips links longer than N characters. 0 turns the check off")
+            .value_name("N")
+            .takes_value(true))
+        .arg(Arg::new("max-query-variants")
+            .global(true)
+            .long("max-query-variants")
+            .about("Crawls at most N distinct query strings of the same path. 0 turns the check off")
+            .value_name("N")
+            .takes_value(true))
+        .arg(Arg::new("check-content-type")
+            .long("check-content-type")
+            .about("Asks the server for the content type of links that do not look like pages with a HEAD request, and records anything but pages as resources"))
+        .arg(Arg::new("check-concurrency")
+            .long("check-concurrency")
+            .about("Sets how many links are checked with --check-content-type at the same time")
+            .value_name("N")
+            .takes_value(true)
+            .default_value("8"))
+        .arg(Arg::new("block-internal-addresses")
+            .global(true)
+            .long("block-internal-addresses")
+            .about("Refuses to connect to loopback, private, link-local and cloud metadata addresses, checking every address a host resolves to"))
+        .arg(Arg::new("strategy")
+            .long("strategy")
+            .about("Sets the order in which pages are visited")
+            .takes_value(true)
+            .possible_values(&["bfs", "dfs", "best-first"])
+            .default_value("bfs"))
+        .arg(Arg::new("score-path")
+            .long("score-path")
+            .about("Adds WEIGHT to the best-first score of links whose path contains PATTERN")
+            .value_name("PATTERN=WEIGHT")
+            .takes_value(true)
+            .multiple_occurrences(true))
+        .arg(Arg::new("score-anchor")
+            .long("score-anchor")
+            .about("Adds WEIGHT to the best-first score of links whose anchor text contains PATTERN")
+            .value_name("PATTERN=WEIGHT")
+            .takes_value(true)
+            .multiple_occurrences(true))
+        .arg(Arg::new("depth-penalty")
+            .long("depth-penalty")
+            .about("Subtracts N from the best-first score for every segment in the link's path")
+            .value_name("N")
+            .takes_value(true)
+            .default_value("0"))
+        .arg(Arg::new("max-pages")
+            .long("max-pages")
+            .about("Stops the crawl after visiting N pages")
+            .value_name("N")
+            .takes_value(true))
+        .arg(Arg::new("concurrency")
+            .long("concurrency")
+            .about("Sets how many pages are fetched at the same time")
+            .value_name("N")
+            .takes_value(true))
+        .arg(Arg::new("bloom-filter")
+            .long("bloom-filter")
+            .about("Remembers visited pages in a fixed size Bloom filter, keeping their links only when checkpointing. A few pages the filter takes for visited are not crawled"))
+        .arg(Arg::new("expected-pages")
+            .long("expected-pages")
+            .about("Sizes the Bloom filter for a crawl of N pages")
+            .value_name("N")
+            .takes_value(true)
+            .default_value("1000000"))
+        .arg(Arg::new("false-positive-rate")
+            .long("false-positive-rate")
+            .about("Sets the share of unvisited pages the Bloom filter may take for visited ones, up to the expected number of pages")
+            .value_name("RATE")
+            .takes_value(true)
+            .default_value("0.001"))
+        .arg(Arg::new("frontier-memory")
+            .long("frontier-memory")
+            .about("Keeps at most N links to visit in memory, and spills the rest to disk")
+            .value_name("N")
+            .takes_value(true))
+        .arg(Arg::new("spill-dir")
+            .long("spill-dir")
+            .about("Sets where links spilled by --frontier-memory are kept. Defaults to the checkpoint directory, or a temporary one")
+            .value_name("DIR")
+            .takes_value(true))
+        .arg(Arg::new("checkpoint")
+            .long("checkpoint")
+            .about("Keeps the state of the crawl in DIR as it goes, so that it can be continued with --resume if it stops")
+            .value_name("DIR")
+            .takes_value(true)
+            .conflicts_with("resume"))
+        .arg(Arg::new("resume")
+            .long("resume")
+            .about("Continues the crawl checkpointed in DIR, without visiting its finished pages again. Seeds default to the checkpointed ones")
+            .value_name("DIR")
+            .takes_value(true))
+        .arg(Arg::new("audit-log")
+            .long("audit-log")
+            .about("Writes every skipped link with the decider that rejected it and why to FILE, or to stderr for -")
+            .value_name("FILE")
+            .takes_value(true))
+        .arg(Arg::new("recrawl")
+            .long("recrawl")
+            .about("Compares the crawl with the one saved in FILE, prints how each page changed to stderr after the pages, and saves it to FILE for the next one")
+            .value_name("FILE")
+            .takes_value(true))
+        .arg(Arg::new("max-revisit-interval")
+            .long("max-revisit-interval")
+            .about("Visits pages that did not change less often with --recrawl, down to once every N crawls")
+            .value_name("N")
+            .takes_value(true)
+            .default_value("1"))
+        .subcommand(App::new("explain")
+            .about("Prints which decider accepts or rejects each URL, using the scope options given, without crawling")
+            .arg(Arg::new("seed")
+                .long("seed")
+                .about("Sets a seed of the crawl being explained. Can be given more than once")
+                .value_name("URL")
+                .takes_value(true)
+                .multiple_occurrences(true))
+            .arg(Arg::new("URL")
+                .about("Sets the URLs to explain. They are read from stdin, one per line, when none are given")
+                .multiple_values(true)
+                .multiple_occurrences(true)
+                .index(1)))
+        .subcommand(App::new("check")
+            .about("Crawls the seeds' site for broken links, checks the links leaving it without crawling them, and prints every broken link with the pages linking to it. Exits with an error when one is found")
+            .arg(Arg::new("SEED")
+                .about("Sets the pages to start checking from")
+                .multiple_values(true)
+                .index(1))
+            .arg(Arg::new("max-pages")
+                .long("max-pages")
+                .about("Stops crawling after visiting N pages. Links on the pages left are checked like the ones leaving the site")
+                .value_name("N")
+                .takes_value(true))
+            .arg(Arg::new("concurrency")
+                .long("concurrency")
+                .about("Sets how many pages are fetched at the same time")
+                .value_name("N")
+                .takes_value(true))
+            .arg(Arg::new("link-concurrency")
+                .long("link-concurrency")
+                .about("Sets how many links outside the crawl are checked at the same time")
+                .value_name("N")
+                .takes_value(true)
+                .default_value("8"))
+            .arg(Arg::new("format")
+                .long("format")
+                .about("Sets how the results are written: the broken links as text, a JUnit XML test case per checked link, or a SARIF log of the broken links on each page linking to them")
+                .takes_value(true)
+                .possible_values(&["text", "junit", "sarif"])
+                .default_value("text"))
+            .arg(Arg::new("output")
+                .long("output")
+                .about("Writes the results to FILE instead of stdout")
+                .value_name("FILE")
+                .takes_value(true))
+            .arg(Arg::new("severity")
+                .long("severity")
+                .about("Sets the severity of broken links. Only errors make the check fail")
+                .takes_value(true)
+                .possible_values(&["error", "warning", "note"])
+                .default_value("error"))
+            .arg(Arg::new("known-broken")
+                .long("known-broken")
+                .about("Reads links known to be broken from FILE, one per line: a URL matched exactly, or a pattern starting with glob: or regex:. They are reported without failing the check")
+                .value_name("FILE")
+                .takes_value(true)))
         .get_matches();
-    let url = matches.value_of("INPUT").unwrap_or("");
-    let (tx, mut rx) = tokio::sync::mpsc::channel::<Vec<String>>(2048);
-    let service = CrawleyScrapeService::new(
-        client::create_client(),
-        queue::create_queue(url)?,
-        TokioResultPublisher::new(tx));
-    let crawly = Crawly::new(service);
-    let links = crawly.start_crawling(url, &mut rx).await?;
-    links.iter().for_each(|link| println!("{:?}", link));
+    if let Some(explain_matches) = matches.subcommand_matches("explain") {
+        return explain(explain_matches);
+    }
+    if let Some(check_matches) = matches.subcommand_matches("check") {
+        return check(check_matches).await;
+    }
+    let seeds = seeds(&matches, "INPUT")?;
+    let (store, restored) = checkpoint(&matches, &seeds)?;
+    let config = crawl_config(&matches)?;
+    let resources = Arc::new(DiscoveredResources::default());
+    let spilled = restored.as_ref().map(|restored| restored.spilled.clone()).unwrap_or_default();
+    let mut builder = CrawlerBuilder::new()
+        .seeds(seeds)
+        .scope(scope_settings(&matches, &config, resources.clone())?)
+        .queue_settings(queue_settings(&matches, store, spilled)?);
+    if let Some(restored) = restored {
+        builder = builder.resume(restored);
+    }
+    let history = match matches.value_of("recrawl") {
+        Some(path) => Some(Arc::new(PageHistory::load(path, u64::from_str(matches.value_of("max-revisit-interval").unwrap_or("1"))?)?)),
+        None => None,
+    };
+    if let Some(history) = &history {
+        builder = builder.recrawl(history.clone());
+    }
+    let mut events = Box::pin(builder.build()?.events());
+    let mut writer = page_writer(&matches)?;
+    let mut summary = matches.value_of("summary").map(|_| CrawlSummary::default());
+    let mut pages = 0;
+    let mut failures = vec![];
+    let mut stopped = None;
+    while let Some(event) = events.next().await {
+        if let Some(summary) = &mut summary {
+            summary.record(&event);
+        }
+        if let CrawlEvent::CrawlFailed { reason } = &event {
+            stopped = Some(reason.clone());
+        }
+        let page = match event.into_page() {
+            Some(page) => page,
+            None => continue,
+        };
+        pages += 1;
+        match writer.write(&page) {
+            // Stops the crawl once whatever reads the pages goes away, as `head` does.
+            Err(err) if err.kind() == ErrorKind::BrokenPipe => return Ok(()),
+            result => result?,
+        }
+        if page.error.is_some() {
+            failures.push(page);
+        }
+    }
+    writer.finish()?;
+    if let (Some(history), Some(path)) = (history, matches.value_of("recrawl")) {
+        history.report().iter().for_each(|(change, link)| eprintln!("{}\t{}", change, link));
+        history.save(path)?;
+    }
+    if matches.is_present("list-resources") {
+        resources.links().iter().for_each(|link| eprintln!("{}", link));
+    }
+    if !failures.is_empty() {
+        eprint!("{}", ErrorSummary::new(pages, &failures));
+    }
+    match (summary, matches.value_of("summary")) {
+        (Some(summary), Some("json")) => eprintln!("{}", serde_json::to_string(&summary)?),
+        (Some(summary), _) => eprint!("{}", summary),
+        _ => {}
+    }
+    match stopped {
+        Some(reason) => Err(format!("The crawl stopped: {}", reason).into()),
+        None => Ok(()),
+    }
+}
+
+fn page_writer(matches: &ArgMatches) -> Result<PageWriter<Box<dyn Write>>, Box<dyn std::error::Error>> {
+    let format = match matches.value_of("format") {
+        Some("json") => OutputFormat::Json,
+        Some("ndjson") => OutputFormat::Ndjson,
+        Some("csv") => OutputFormat::Csv,
+        _ => OutputFormat::Plain,
+    };
+    Ok(PageWriter::new(format, output(matches)?))
+}
+
+/// Where `--output` points, or stdout.
+fn output(matches: &ArgMatches) -> Result<Box<dyn Write>, Box<dyn std::error::Error>> {
+    Ok(match matches.value_of("output") {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(std::io::stdout()),
+    })
+}
+
+/// Crawls the site, then checks the links the crawl did not visit, and reports the broken ones.
+async fn check(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let seeds = seeds(matches, "SEED")?;
+    if seeds.is_empty() {
+        return Err("check needs at least one seed".into());
+    }
+    let config = crawl_config(matches)?;
+    let crawler = CrawlerBuilder::new()
+        .seeds(seeds)
+        .scope(scope_settings(matches, &config, Arc::new(DiscoveredResources::default()))?)
+        .queue_settings(queue_settings(matches, None, vec![])?)
+        .build()?;
+    let mut graph = LinkGraph::default();
+    let mut events = Box::pin(crawler.events());
+    while let Some(event) = events.next().await {
+        match event {
+            CrawlEvent::CrawlFailed { reason } => return Err(format!("The crawl stopped: {}", reason).into()),
+            event => if let Some(page) = event.into_page() {
+                graph.record(&page);
+            },
+        }
+    }
+    let checker = LinkChecker::new(
+        create_http_client(address_policy(matches)),
+        usize::from_str(matches.value_of("link-concurrency").unwrap_or("8"))?);
+    for (link, outcome) in checker.check_all(graph.unchecked()).await {
+        graph.checked(&link, outcome);
+    }
+    let known_broken = match matches.value_of("known-broken") {
+        Some(path) => KnownBroken::load(path)?,
+        None => KnownBroken::default(),
+    };
+    let severity = match matches.value_of("severity") {
+        Some("warning") => Severity::Warning,
+        Some("note") => Severity::Note,
+        _ => Severity::Error,
+    };
+    let format = match matches.value_of("format") {
+        Some("junit") => ReportFormat::Junit,
+        Some("sarif") => ReportFormat::Sarif,
+        _ => ReportFormat::Text,
+    };
+    let links = graph.links();
+    let report = LinkReport::new(&links, &known_broken, severity);
+    report.write(format, output(matches)?)?;
+    eprintln!("{} of {} links are broken", report.broken().len(), links.len());
+    if !report.known().is_empty() {
+        eprintln!("{} more links are known to be broken", report.known().len());
+    }
+    if report.fails() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn explain(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let seeds = seeds(matches, "seed")?;
+    let base = match seeds.first() {
+        Some(seed) => LinkConstructor::try_from(seed.as_str())?,
+        None => return Err("explain needs at least one --seed".into()),
+    };
+    let config = crawl_config(matches)?;
+    let queue = queue::create_queue(
+        &seeds,
+        scope_settings(matches, &config, Arc::new(DiscoveredResources::default()))?,
+        QueueSettings::default())?;
+    let candidates: Vec<String> = match matches.values_of("URL") {
+        Some(values) => values.map(|value| value.to_string()).collect(),
+        None => BufReader::new(std::io::stdin()).lines().collect::<Result<_, _>>()?,
+    };
+    candidates.iter()
+        .map(|candidate| candidate.trim())
+        .filter(|candidate| !candidate.is_empty())
+        .for_each(|candidate| println!("{}", explain::explain(&queue, &base, candidate)));
     Ok(())
 }
 
+fn crawl_config(matches: &ArgMatches) -> Result<CrawlConfig, Box<dyn std::error::Error>> {
+    Ok(match matches.value_of("config") {
+        Some(path) => CrawlConfig::load(path)?,
+        None => CrawlConfig::default(),
+    })
+}
+
+fn seeds(matches: &ArgMatches, name: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut seeds: Vec<String> = matches.values_of(name)
+        .map(|values| values.map(|value| value.to_string()).collect())
+        .unwrap_or_default();
+    if let Some(path) = matches.value_of("seed-file") {
+        let reader: Box<dyn Read> = if path == "-" {
+            Box::new(std::io::stdin())
+        } else {
+            Box::new(File::open(path)?)
+        };
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let seed = line.trim();
+            if !seed.is_empty() && !seed.starts_with('#') {
+                seeds.push(seed.to_string());
+            }
+        }
+    }
+    Ok(seeds)
+}
+
+fn scope_settings(matches: &ArgMatches, config: &CrawlConfig, resources: Arc<DiscoveredResources>) -> Result<ScopeSettings, Box<dyn std::error::Error>> {
+    Ok(ScopeSettings {
+        include: url_patterns(&config.include, matches, "include")?,
+        exclude: url_patterns(&config.exclude, matches, "exclude")?,
+        site: site_policy(matches, &config.site),
+        restrict_to_seed_path: config.restrict_to_seed_path || matches.is_present("restrict-to-seed-path"),
+        path_prefixes: strings(&config.path_prefixes, matches, "path-prefix"),
+        resource_types: ResourceTypePolicy {
+            allow: strings(&config.resources.allow, matches, "allow-type"),
+            deny: strings(&config.resources.deny, matches, "deny-type"),
+        },
+        resources,
+        traps: trap_thresholds(matches, &config.traps)?,
+        check_content_type: matches.is_present("check-content-type"),
+        check_concurrency: usize::from_str(matches.value_of("check-concurrency").unwrap_or("8"))?,
+        address_policy: address_policy(matches),
+        deciders: vec![],
+    })
+}
+
+fn address_policy(matches: &ArgMatches) -> AddressPolicy {
+    if matches.is_present("block-internal-addresses") {
+        AddressPolicy::BlockInternal
+    } else {
+        AddressPolicy::AllowAll
+    }
+}
+
+fn trap_thresholds(matches: &ArgMatches, configured: &TrapThresholds) -> Result<TrapThresholds, Box<dyn std::error::Error>> {
+    let threshold = |name: &str, configured: usize| -> Result<usize, Box<dyn std::error::Error>> {
+        Ok(matches.value_of(name).map(usize::from_str).transpose()?.unwrap_or(configured))
+    };
+    Ok(TrapThresholds {
+        max_segment_repeats: threshold("max-segment-repeats", configured.max_segment_repeats)?,
+        max_path_depth: threshold("max-path-depth", configured.max_path_depth)?,
+        max_url_length: threshold("max-url-length", configured.max_url_length)?,
+        max_query_variants: threshold("max-query-variants", configured.max_query_variants)?,
+    })
+}
+
+fn strings(configured: &[String], matches: &ArgMatches, name: &str) -> Vec<String> {
+    configured.iter()
+        .map(|value| value.as_str())
+        .chain(matches.values_of(name).into_iter().flatten())
+        .map(|value| value.to_string())
+        .collect()
+}
+
+fn site_policy(matches: &ArgMatches, configured: &SitePolicy) -> SitePolicy {
+    SitePolicy {
+        include_subdomains: configured.include_subdomains || matches.is_present("include-subdomains"),
+        www_equivalent: configured.www_equivalent || matches.is_present("www-equivalent"),
+        match_scheme: configured.match_scheme || matches.is_present("match-scheme"),
+        match_port: configured.match_port || matches.is_present("match-port"),
+        public_suffix_list: matches.value_of("public-suffix-list")
+            .map(|path| path.to_string())
+            .or_else(|| configured.public_suffix_list.clone()),
+    }
+}
+
+fn url_patterns(configured: &[String], matches: &ArgMatches, name: &str) -> Result<Vec<UrlPattern>, Box<dyn std::error::Error>> {
+    let patterns = configured.iter()
+        .map(|pattern| pattern.as_str())
+        .chain(matches.values_of(name).into_iter().flatten())
+        .map(UrlPattern::try_from)
+        .collect::<Result<Vec<UrlPattern>, _>>()?;
+    Ok(patterns)
+}
+
+/// Where the crawl is checkpointed, and the checkpoint it resumes from.
+type Checkpointing = (Option<Arc<CheckpointStore>>, Option<Checkpoint>);
+
+fn checkpoint(matches: &ArgMatches, seeds: &[String]) -> Result<Checkpointing, Box<dyn std::error::Error>> {
+    if let Some(directory) = matches.value_of("resume") {
+        let (store, checkpoint) = CheckpointStore::resume(directory)?;
+        return Ok((Some(Arc::new(store)), Some(checkpoint)));
+    }
+    match matches.value_of("checkpoint") {
+        Some(directory) => Ok((Some(Arc::new(CheckpointStore::create(directory, seeds)?)), None)),
+        None => Ok((None, None)),
+    }
+}
 
+fn queue_settings(matches: &ArgMatches, checkpoint: Option<Arc<CheckpointStore>>, spilled: Vec<SegmentInfo>) -> Result<QueueSettings, Box<dyn std::error::Error>> {
+    let strategy = match matches.value_of("strategy") {
+        Some("dfs") => CrawlStrategy::DepthFirst,
+        Some("best-first") => CrawlStrategy::BestFirst(Box::new(PatternScorer::new(
+            score_rules(matches, "score-path")?,
+            score_rules(matches, "score-anchor")?,
+            i64::from_str(matches.value_of("depth-penalty").unwrap_or("0"))?))),
+        _ => CrawlStrategy::BreadthFirst,
+    };
+    Ok(QueueSettings {
+        strategy,
+        max_pages: matches.value_of("max-pages").map(usize::from_str).transpose()?,
+        batch_size: matches.value_of("concurrency").map(usize::from_str).transpose()?,
+        audit_log: audit_log(matches)?,
+        checkpoint,
+        bloom_filter: bloom_filter(matches)?,
+        frontier_segments: frontier_segments(matches, spilled)?,
+    })
+}
 
+/// Where links over the frontier's memory limit go: the given directory, the checkpoint's,
+/// so that they are resumed with it, or a temporary one.
+fn frontier_segments(matches: &ArgMatches, spilled: Vec<SegmentInfo>) -> Result<Option<FrontierSegments>, Box<dyn std::error::Error>> {
+    let memory_limit = match matches.value_of("frontier-memory") {
+        Some(limit) => usize::from_str(limit)?,
+        None => return Ok(None),
+    };
+    let checkpoint = matches.value_of("checkpoint").or_else(|| matches.value_of("resume"));
+    let directory = match (matches.value_of("spill-dir"), checkpoint) {
+        (Some(directory), _) => PathBuf::from(directory),
+        (None, Some(checkpoint)) => PathBuf::from(checkpoint).join("frontier"),
+        (None, None) => std::env::temp_dir().join(format!("crawly-frontier-{}", std::process::id())),
+    };
+    Ok(Some(FrontierSegments::open(&directory.to_string_lossy(), memory_limit, spilled)?))
+}
+
+fn bloom_filter(matches: &ArgMatches) -> Result<Option<BloomFilterSettings>, Box<dyn std::error::Error>> {
+    if !matches.is_present("bloom-filter") {
+        return Ok(None);
+    }
+    Ok(Some(BloomFilterSettings::new(
+        usize::from_str(matches.value_of("expected-pages").unwrap_or("1000000"))?,
+        f64::from_str(matches.value_of("false-positive-rate").unwrap_or("0.001"))?)?))
+}
+
+fn audit_log(matches: &ArgMatches) -> Result<Option<Arc<RejectionLog>>, Box<dyn std::error::Error>> {
+    let writer: Box<dyn Write + Send> = match matches.value_of("audit-log") {
+        Some("-") => Box::new(std::io::stderr()),
+        Some(path) => Box::new(File::create(path)?),
+        None => return Ok(None),
+    };
+    Ok(Some(Arc::new(RejectionLog::new(writer))))
+}
+
+fn score_rules(matches: &ArgMatches, name: &str) -> Result<Vec<ScoreRule>, Box<dyn std::error::Error>> {
+    let rules = matches.values_of(name)
+        .map(|values| values.map(ScoreRule::try_from).collect::<Result<Vec<ScoreRule>, _>>())
+        .transpose()?;
+    Ok(rules.unwrap_or_default())
+}
@@ -0,0 +1,3 @@
+pub use page_writer::{OutputFormat, PageWriter};
+
+mod page_writer;
@@ -0,0 +1,172 @@
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::page::PageResult;
+
+/// How pages are written out.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OutputFormat {
+    /// One URL per line.
+    Plain,
+    /// A JSON array of records.
+    Json,
+    /// One JSON record per line.
+    Ndjson,
+    /// A header row, then one record per row.
+    Csv,
+}
+
+const CSV_HEADER: &str = "url,final_url,status,depth,referrer,content_type,error,error_message";
+
+/// What is written of a page. Fields are only ever added at the end, so that readers of the
+/// output keep working. Missing values are null in JSON and empty in CSV.
+#[derive(Debug, Serialize)]
+struct OutputRecord<'a> {
+    url: &'a str,
+    final_url: &'a str,
+    status: Option<u16>,
+    depth: usize,
+    referrer: Option<&'a str>,
+    content_type: Option<&'a str>,
+    error: Option<&'static str>,
+    error_message: Option<String>,
+}
+
+impl<'a> OutputRecord<'a> {
+    fn new(page: &'a PageResult) -> OutputRecord<'a> {
+        OutputRecord {
+            url: &page.url,
+            final_url: &page.final_url,
+            status: page.status,
+            depth: page.depth,
+            referrer: page.referrer.as_deref(),
+            content_type: page.headers.get("content-type").map(|value| value.as_str()),
+            error: page.error.as_ref().map(|err| err.kind()),
+            error_message: page.error.as_ref().map(|err| err.message()),
+        }
+    }
+
+    fn csv_row(&self) -> String {
+        let status = self.status.map(|status| status.to_string()).unwrap_or_default();
+        let depth = self.depth.to_string();
+        let error_message = self.error_message.clone().unwrap_or_default();
+        [
+            self.url,
+            self.final_url,
+            &status,
+            &depth,
+            self.referrer.unwrap_or_default(),
+            self.content_type.unwrap_or_default(),
+            self.error.unwrap_or_default(),
+            &error_message,
+        ].iter().map(|field| csv_field(field)).collect::<Vec<String>>().join(",")
+    }
+}
+
+/// Quotes a CSV field when it holds a separator, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Writes pages as they come, flushing each one so that the output can be read while the
+/// crawl runs. `finish` completes the output, closing the array of the JSON format.
+pub struct PageWriter<W: Write> {
+    format: OutputFormat,
+    writer: W,
+    written: usize,
+}
+
+impl<W: Write> PageWriter<W> {
+    pub fn new(format: OutputFormat, writer: W) -> PageWriter<W> {
+        PageWriter { format, writer, written: 0 }
+    }
+
+    pub fn write(&mut self, page: &PageResult) -> io::Result<()> {
+        let record = OutputRecord::new(page);
+        match self.format {
+            OutputFormat::Plain => writeln!(self.writer, "{}", page.url)?,
+            OutputFormat::Json => {
+                let separator = if self.written == 0 { "[\n" } else { ",\n" };
+                write!(self.writer, "{}{}", separator, serde_json::to_string(&record)?)?;
+            }
+            OutputFormat::Ndjson => writeln!(self.writer, "{}", serde_json::to_string(&record)?)?,
+            OutputFormat::Csv => {
+                if self.written == 0 {
+                    writeln!(self.writer, "{}", CSV_HEADER)?;
+                }
+                writeln!(self.writer, "{}", record.csv_row())?;
+            }
+        }
+        self.written += 1;
+        self.writer.flush()
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        match (self.format, self.written) {
+            (OutputFormat::Json, 0) => writeln!(self.writer, "[]")?,
+            (OutputFormat::Json, _) => writeln!(self.writer, "\n]")?,
+            (OutputFormat::Csv, 0) => writeln!(self.writer, "{}", CSV_HEADER)?,
+            _ => {}
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::CrawlClientError;
+    use crate::output::{OutputFormat, PageWriter};
+    use crate::page::PageResult;
+    use crate::queue::FrontierEntry;
+
+    fn pages() -> Vec<PageResult> {
+        let seed = FrontierEntry::seed("https://domain.com/");
+        let mut home = PageResult::new(&seed);
+        home.status = Some(200);
+        home.headers.insert("content-type".to_string(), "text/html".to_string());
+        let missing = PageResult::failed(&FrontierEntry::discovered("https://domain.com/a,b", &seed, "a"), CrawlClientError::HttpError(404));
+        vec![home, missing]
+    }
+
+    fn written(format: OutputFormat, pages: &[PageResult]) -> String {
+        let mut writer = PageWriter::new(format, vec![]);
+        pages.iter().for_each(|page| writer.write(page).unwrap());
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn should_write_one_url_per_line_in_plain_format() {
+        assert_eq!(written(OutputFormat::Plain, &pages()), "https://domain.com/\nhttps://domain.com/a,b\n")
+    }
+
+    #[test]
+    fn should_write_one_record_per_line_in_ndjson_format() {
+        let output = written(OutputFormat::Ndjson, &pages());
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[0], r#"{"url":"https://domain.com/","final_url":"https://domain.com/","status":200,"depth":0,"referrer":null,"content_type":"text/html","error":null,"error_message":null}"#);
+        assert_eq!(lines[1], r#"{"url":"https://domain.com/a,b","final_url":"https://domain.com/a,b","status":404,"depth":1,"referrer":"https://domain.com/","content_type":null,"error":"http","error_message":"The page responded with status 404"}"#);
+    }
+
+    #[test]
+    fn should_write_a_json_array_even_without_pages() {
+        let output: serde_json::Value = serde_json::from_str(&written(OutputFormat::Json, &pages())).unwrap();
+
+        assert_eq!(output.as_array().map(|pages| pages.len()), Some(2));
+        assert_eq!(output[1]["status"], 404);
+        assert_eq!(written(OutputFormat::Json, &[]), "[]\n")
+    }
+
+    #[test]
+    fn should_write_a_header_and_quote_fields_in_csv_format() {
+        assert_eq!(written(OutputFormat::Csv, &pages()), "url,final_url,status,depth,referrer,content_type,error,error_message\n\
+            https://domain.com/,https://domain.com/,200,0,,text/html,,\n\
+            \"https://domain.com/a,b\",\"https://domain.com/a,b\",404,1,https://domain.com/,,http,The page responded with status 404\n")
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use crate::page::PageResult;
+
+/// The pages of a crawl, the ones that were visited apart from the ones that failed.
+#[derive(Debug, Default)]
+pub struct CrawlResults {
+    pub successes: Vec<PageResult>,
+    pub failures: Vec<PageResult>,
+    /// Why the crawl stopped before it was finished, when it did.
+    pub error: Option<String>,
+}
+
+impl CrawlResults {
+    pub fn add(&mut self, page: PageResult) {
+        if page.error.is_some() {
+            self.failures.push(page);
+        } else {
+            self.successes.push(page);
+        }
+    }
+
+    pub fn error_summary(&self) -> ErrorSummary<'_> {
+        ErrorSummary::new(self.successes.len() + self.failures.len(), &self.failures)
+    }
+}
+
+/// How many of the pages failed and of which kind of error, then every failed page with the
+/// page that linked to it and what went wrong.
+pub struct ErrorSummary<'a> {
+    pages: usize,
+    failures: &'a [PageResult],
+}
+
+impl<'a> ErrorSummary<'a> {
+    pub fn new(pages: usize, failures: &'a [PageResult]) -> ErrorSummary<'a> {
+        ErrorSummary { pages, failures }
+    }
+
+    /// The number of failures of each kind of error, by kind.
+    pub fn kinds(&self) -> BTreeMap<&'static str, usize> {
+        let mut kinds = BTreeMap::new();
+        for error in self.failures.iter().filter_map(|page| page.error.as_ref()) {
+            *kinds.entry(error.kind()).or_insert(0) += 1;
+        }
+        kinds
+    }
+}
+
+impl Display for ErrorSummary<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} of {} pages failed", self.failures.len(), self.pages)?;
+        for (kind, count) in self.kinds() {
+            writeln!(f, "  {}: {}", kind, count)?;
+        }
+        for page in self.failures {
+            let message = page.error.as_ref().map(|err| err.message()).unwrap_or_default();
+            match &page.referrer {
+                Some(referrer) => writeln!(f, "{} (linked from {}): {}", page.url, referrer, message)?,
+                None => writeln!(f, "{}: {}", page.url, message)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::CrawlClientError;
+    use crate::page::{CrawlResults, PageResult};
+    use crate::queue::FrontierEntry;
+
+    #[test]
+    fn should_keep_failures_apart_and_summarize_them_by_kind() {
+        let seed = FrontierEntry::seed("https://domain.com/");
+        let mut results = CrawlResults::default();
+        results.add(PageResult::new(&seed));
+        results.add(PageResult::failed(&FrontierEntry::discovered("https://domain.com/missing", &seed, "missing"), CrawlClientError::HttpError(404)));
+        results.add(PageResult::failed(&FrontierEntry::seed("https://down.com/"), CrawlClientError::ConnectionError));
+
+        assert_eq!(results.successes.len(), 1);
+        assert_eq!(results.failures.len(), 2);
+        assert_eq!(results.error_summary().to_string(), "2 of 3 pages failed\n  connection: 1\n  http: 1\n\
+            https://domain.com/missing (linked from https://domain.com/): The page responded with status 404\n\
+            https://down.com/: There was an error connection to the page\n")
+    }
+}
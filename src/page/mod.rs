@@ -0,0 +1,5 @@
+pub use crawl_results::{CrawlResults, ErrorSummary};
+pub use page_result::{Link, PageResult, Timings};
+
+mod crawl_results;
+mod page_result;
@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::client::CrawlClientError;
+use crate::queue::FrontierEntry;
+
+/// A link found on a page, made absolute against the page it was found on. Its fragment is
+/// kept, though pages are visited without it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Link {
+    pub url: String,
+    pub text: String,
+}
+
+impl Link {
+    pub fn new(url: &str, text: &str) -> Link {
+        Link { url: url.to_string(), text: text.to_string() }
+    }
+}
+
+/// How long a visit took: until the response headers came in, when there was a response,
+/// and in all.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Timings {
+    pub time_to_headers: Option<Duration>,
+    pub total: Duration,
+}
+
+/// What a visit to a page found. `final_url` is where redirects led, and outlinks are made
+/// absolute against it. Fragments are the ids and `<a name>`s on the page. A page that could
+/// not be fetched has its error, and the status when the server answered with one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PageResult {
+    pub url: String,
+    pub final_url: String,
+    pub status: Option<u16>,
+    pub headers: BTreeMap<String, String>,
+    pub depth: usize,
+    pub referrer: Option<String>,
+    pub timings: Timings,
+    pub error: Option<CrawlClientError>,
+    pub outlinks: Vec<Link>,
+    pub fragments: Vec<String>,
+    pub bytes: usize,
+    pub content_hash: Option<String>,
+}
+
+impl PageResult {
+    /// A result for the entry with nothing found yet.
+    pub fn new(entry: &FrontierEntry) -> PageResult {
+        PageResult {
+            url: entry.url.clone(),
+            final_url: entry.url.clone(),
+            status: None,
+            headers: BTreeMap::new(),
+            depth: entry.depth,
+            referrer: entry.referrer.clone(),
+            timings: Timings::default(),
+            error: None,
+            outlinks: vec![],
+            fragments: vec![],
+            bytes: 0,
+            content_hash: None,
+        }
+    }
+
+    pub fn failed(entry: &FrontierEntry, error: CrawlClientError) -> PageResult {
+        let status = match error {
+            CrawlClientError::HttpError(status) => Some(status),
+            _ => None,
+        };
+        PageResult { status, error: Some(error), ..PageResult::new(entry) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::CrawlClientError;
+    use crate::page::PageResult;
+    use crate::queue::FrontierEntry;
+
+    #[test]
+    fn should_keep_the_status_of_http_errors() {
+        let entry = FrontierEntry::discovered("https://domain.com/missing", &FrontierEntry::seed("https://domain.com/"), "missing");
+
+        let result = PageResult::failed(&entry, CrawlClientError::HttpError(404));
+
+        assert_eq!(result.status, Some(404));
+        assert_eq!(result.depth, 1);
+        assert_eq!(result.referrer, Some("https://domain.com/".to_string()));
+        assert_eq!(result.error, Some(CrawlClientError::HttpError(404)))
+    }
+}
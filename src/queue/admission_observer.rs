@@ -0,0 +1,14 @@
+use crate::queue::queue_addition_decider::Decision;
+
+/// Told what the queue made of links found for the first time. Deciders only decide, so
+/// that their decisions can be explained without side effects; whatever is kept about the
+/// links of a crawl is kept by an observer.
+#[cfg_attr(test, mockall::automock)]
+pub trait AdmissionObserver: Sync + Send {
+    /// The link passed the sync deciders and holds a place in the queue. An async decider
+    /// may still turn it down.
+    fn queued(&self, _link: &str) {}
+
+    /// A decider turned the link down.
+    fn rejected(&self, _link: &str, _decision: &Decision) {}
+}
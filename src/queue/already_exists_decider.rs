@@ -1,24 +1,32 @@
 use flurry::HashSet;
-use crate::queue::queue_addition_decider::QueueAdditionDecider;
+use crate::queue::queue_addition_decider::{Decision, QueueAdditionDecider};
+use crate::queue::seen_set::SeenSet;
+
+pub const ALREADY_SEEN: &str = "already-seen";
 
 pub struct ItemAlreadyExistsDecider<'a> {
     queue: &'a HashSet<String>,
-    processed: &'a HashSet<String>,
+    processed: &'a SeenSet,
 }
 
 impl <'a> QueueAdditionDecider for ItemAlreadyExistsDecider<'a> {
-    fn can_add_to_queue(&self, link: &str) -> bool {
-        let finished_guard = self.processed.guard();
+    fn decide(&self, link: &str) -> Decision {
         let queue_gaurd = self.queue.guard();
-        !self.processed.contains(link, &finished_guard) && !self.queue.contains(link, &queue_gaurd)
+        if self.processed.contains(link) {
+            Decision::reject(ALREADY_SEEN, "already crawled")
+        } else if self.queue.contains(link, &queue_gaurd) {
+            Decision::reject(ALREADY_SEEN, "already queued")
+        } else {
+            Decision::accept(ALREADY_SEEN, "not seen before")
+        }
     }
 }
 
 impl <'a> ItemAlreadyExistsDecider<'a> {
-    pub fn new(queue: &'a HashSet<String>, processed: &'a HashSet<String>) -> ItemAlreadyExistsDecider<'a> {
+    pub fn new(queue: &'a HashSet<String>, processed: &'a SeenSet) -> ItemAlreadyExistsDecider<'a> {
         ItemAlreadyExistsDecider {
-            queue: &queue,
-            processed: &processed
+            queue,
+            processed
         }
     }
 }
@@ -28,15 +36,16 @@ mod tests {
     use flurry::HashSet;
     use crate::queue::already_exists_decider::ItemAlreadyExistsDecider;
     use crate::queue::queue_addition_decider::QueueAdditionDecider;
+    use crate::queue::seen_set::SeenSet;
 
     #[test]
     fn should_decide_true_when_link_not_already_in_queue() {
         let queue = HashSet::<String>::new();
         queue.insert("http://domain.com/page1.html".to_string(), &queue.guard());
-        let processed = HashSet::<String>::new();
+        let processed = SeenSet::default();
         let decider = ItemAlreadyExistsDecider::new(&queue, &processed);
 
-        let decision = decider.can_add_to_queue("http://domain.com/page2.html");
+        let decision = decider.decide("http://domain.com/page2.html").accepted;
 
         assert!(decision)
     }
@@ -44,11 +53,11 @@ mod tests {
     #[test]
     fn should_decide_true_when_link_not_already_in_processed() {
         let queue = HashSet::<String>::new();
-        let processed = HashSet::<String>::new();
-        processed.insert("http://domain.com/page1.html".to_string(), &processed.guard());
+        let processed = SeenSet::default();
+        processed.insert("http://domain.com/page1.html");
         let decider = ItemAlreadyExistsDecider::new(&queue, &processed);
 
-        let decision = decider.can_add_to_queue("http://domain.com/page2.html");
+        let decision = decider.decide("http://domain.com/page2.html").accepted;
 
         assert!(decision)
     }
@@ -57,10 +66,10 @@ mod tests {
     fn should_decide_false_when_link_already_in_queue() {
         let queue = HashSet::<String>::new();
         queue.insert("http://domain.com/page1.html".to_string(), &queue.guard());
-        let processed = HashSet::<String>::new();
+        let processed = SeenSet::default();
         let decider = ItemAlreadyExistsDecider::new(&queue, &processed);
 
-        let decision = decider.can_add_to_queue("http://domain.com/page1.html");
+        let decision = decider.decide("http://domain.com/page1.html").accepted;
 
         assert!(!decision)
     }
@@ -68,11 +77,11 @@ mod tests {
     #[test]
     fn should_decide_false_when_link_already_in_processed() {
         let queue = HashSet::<String>::new();
-        let processed = HashSet::<String>::new();
-        processed.insert("http://domain.com/page1.html".to_string(), &processed.guard());
+        let processed = SeenSet::default();
+        processed.insert("http://domain.com/page1.html");
         let decider = ItemAlreadyExistsDecider::new(&queue, &processed);
 
-        let decision = decider.can_add_to_queue("http://domain.com/page1.html");
+        let decision = decider.decide("http://domain.com/page1.html").accepted;
 
         assert!(!decision)
     }
@@ -0,0 +1,78 @@
+use tokio::sync::Semaphore;
+
+use crate::queue::queue_addition_decider::Decision;
+
+/// A decider that needs I/O, such as a request or a DNS lookup, to make up its mind. Async
+/// deciders run after the sync ones accepted a link and it was reserved in the queue.
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait AsyncQueueAdditionDecider: Sync + Send {
+    async fn decide(&self, link: &str) -> Decision;
+}
+
+/// Runs at most `concurrency` decisions of an async decider at once. Decisions are not
+/// remembered: the queue already decides on each link only once.
+pub struct LimitedDecider {
+    decider: Box<dyn AsyncQueueAdditionDecider>,
+    permits: Semaphore,
+}
+
+impl LimitedDecider {
+    pub fn new(decider: Box<dyn AsyncQueueAdditionDecider>, concurrency: usize) -> LimitedDecider {
+        LimitedDecider {
+            decider,
+            permits: Semaphore::new(concurrency.max(1)),
+        }
+    }
+}
+
+#[async_trait]
+impl AsyncQueueAdditionDecider for LimitedDecider {
+    async fn decide(&self, link: &str) -> Decision {
+        let _permit = self.permits.acquire().await;
+        self.decider.decide(link).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::future::join_all;
+
+    use crate::queue::async_decider::{AsyncQueueAdditionDecider, LimitedDecider};
+    use crate::queue::queue_addition_decider::Decision;
+
+    struct CountingDecider {
+        running: Arc<AtomicUsize>,
+        most_running: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl AsyncQueueAdditionDecider for CountingDecider {
+        async fn decide(&self, link: &str) -> Decision {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.most_running.fetch_max(running, Ordering::SeqCst);
+            let _ = tokio::task::yield_now().await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            Decision::accept("counting", link)
+        }
+    }
+
+    #[tokio::test]
+    async fn should_limit_how_many_decisions_run_at_once() {
+        let most_running = Arc::new(AtomicUsize::new(0));
+        let limited = LimitedDecider::new(Box::new(CountingDecider {
+            running: Arc::new(AtomicUsize::new(0)),
+            most_running: most_running.clone(),
+        }), 2);
+
+        join_all((0..10).map(|page| format!("https://example.com/{}", page))
+            .collect::<Vec<String>>()
+            .iter()
+            .map(|link| limited.decide(link))).await;
+
+        assert_eq!(most_running.load(Ordering::SeqCst), 2)
+    }
+}
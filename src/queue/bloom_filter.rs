@@ -0,0 +1,122 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::queue::QueueConfigurationError;
+
+/// How big a Bloom filter is made: for how many pages, and how often it may take a page it
+/// has not seen for one it has.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BloomFilterSettings {
+    pub expected_pages: usize,
+    pub false_positive_rate: f64,
+}
+
+impl BloomFilterSettings {
+    pub fn new(expected_pages: usize, false_positive_rate: f64) -> Result<BloomFilterSettings, QueueConfigurationError> {
+        if expected_pages == 0 {
+            return Err(QueueConfigurationError::InvalidBloomFilter("expected pages must be more than 0".to_string()));
+        }
+        if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            return Err(QueueConfigurationError::InvalidBloomFilter(format!("false positive rate {} must be between 0 and 1", false_positive_rate)));
+        }
+        Ok(BloomFilterSettings { expected_pages, false_positive_rate })
+    }
+}
+
+/// A fixed size set of links that may answer that it contains a link it does not, at the
+/// configured rate, but never the other way around. Its memory is set up front from the
+/// expected number of pages and does not grow. The rate goes up once more pages than
+/// expected were added.
+pub struct BloomFilter {
+    bits: Vec<AtomicU64>,
+    bit_count: u64,
+    hash_count: u32,
+}
+
+impl BloomFilter {
+    pub fn new(settings: BloomFilterSettings) -> BloomFilter {
+        let items = settings.expected_pages as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bit_count = (-items * settings.false_positive_rate.ln() / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let hash_count = ((bit_count as f64 / items) * ln2).round().max(1.0) as u32;
+        BloomFilter {
+            bits: (0..bit_count.div_ceil(64)).map(|_| AtomicU64::new(0)).collect(),
+            bit_count,
+            hash_count,
+        }
+    }
+
+    pub fn contains(&self, link: &str) -> bool {
+        self.positions(link).all(|(word, mask)| self.bits[word].load(Ordering::SeqCst) & mask != 0)
+    }
+
+    /// Adds the link, returning false when it was, or seemed to be, there already.
+    pub fn insert(&self, link: &str) -> bool {
+        let mut added = false;
+        for (word, mask) in self.positions(link) {
+            added |= self.bits[word].fetch_or(mask, Ordering::SeqCst) & mask == 0;
+        }
+        added
+    }
+
+    /// The bits of a link, from two hashes combined as in Kirsch and Mitzenmacher's
+    /// "Less hashing, same performance".
+    fn positions(&self, link: &str) -> impl Iterator<Item = (usize, u64)> + '_ {
+        let first = hash(link, 0);
+        let second = hash(link, 1) | 1;
+        (0..self.hash_count as u64).map(move |index| {
+            let bit = first.wrapping_add(index.wrapping_mul(second)) % self.bit_count;
+            ((bit / 64) as usize, 1u64 << (bit % 64))
+        })
+    }
+
+    #[cfg(test)]
+    fn memory(&self) -> usize {
+        self.bits.len() * 8
+    }
+}
+
+fn hash(link: &str, seed: u8) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    link.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::queue::QueueConfigurationError;
+    use crate::queue::bloom_filter::{BloomFilter, BloomFilterSettings};
+
+    #[test]
+    fn should_contain_every_inserted_link() {
+        let filter = BloomFilter::new(BloomFilterSettings::new(1000, 0.01).unwrap());
+
+        let added: Vec<bool> = (0..1000).map(|page| filter.insert(&format!("https://domain.com/{}", page))).collect();
+
+        assert!(added.iter().filter(|added| !**added).count() < 30);
+        assert!((0..1000).all(|page| filter.contains(&format!("https://domain.com/{}", page))));
+        assert!(!filter.insert("https://domain.com/0"))
+    }
+
+    #[test]
+    fn should_keep_false_positives_near_the_configured_rate() {
+        let filter = BloomFilter::new(BloomFilterSettings::new(10_000, 0.01).unwrap());
+        (0..10_000).for_each(|page| { filter.insert(&format!("https://domain.com/page/{}", page)); });
+
+        let false_positives = (0..10_000)
+            .filter(|page| filter.contains(&format!("https://other.com/page/{}", page)))
+            .count();
+
+        assert!(false_positives < 200, "{} false positives", false_positives);
+        assert!(filter.memory() < 16 * 1024)
+    }
+
+    #[test]
+    fn should_refuse_rates_outside_zero_and_one() {
+        assert!(matches!(BloomFilterSettings::new(100, 1.0), Err(QueueConfigurationError::InvalidBloomFilter(_))));
+        assert!(matches!(BloomFilterSettings::new(100, 0.0), Err(QueueConfigurationError::InvalidBloomFilter(_))));
+        assert!(matches!(BloomFilterSettings::new(0, 0.1), Err(QueueConfigurationError::InvalidBloomFilter(_))))
+    }
+}
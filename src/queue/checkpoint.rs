@@ -0,0 +1,267 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::queue::error::CheckpointError;
+use crate::queue::frontier::FrontierEntry;
+use crate::queue::frontier_segments::SegmentInfo;
+
+const SNAPSHOT_FILE: &str = "checkpoint.json";
+const JOURNAL_FILE: &str = "journal.ndjson";
+pub const DEFAULT_SNAPSHOT_EVERY: usize = 10_000;
+
+/// The state of a crawl: the seeds it started from, the pages it visited, the links still to
+/// visit with their depth and anchor text, the frontier segments spilled to disk, and the
+/// links async deciders turned down.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Checkpoint {
+    pub seeds: Vec<String>,
+    pub processed: Vec<String>,
+    pub pending: Vec<FrontierEntry>,
+    pub spilled: Vec<SegmentInfo>,
+    pub rejected: Vec<String>,
+}
+
+/// A change to the state of the crawl, appended to the journal as it happens.
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalEvent {
+    Queued(FrontierEntry),
+    Done(String),
+    Rejected(String),
+}
+
+impl Checkpoint {
+    fn replay(self, events: Vec<JournalEvent>) -> Checkpoint {
+        let mut processed: Vec<String> = vec![];
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut rejected: HashSet<String> = self.rejected.into_iter().collect();
+        let mut order: Vec<String> = vec![];
+        let mut pending: HashMap<String, FrontierEntry> = HashMap::new();
+        for link in self.processed {
+            if seen.insert(link.clone()) {
+                processed.push(link);
+            }
+        }
+        for entry in self.pending {
+            order.push(entry.url.clone());
+            pending.insert(entry.url.clone(), entry);
+        }
+        for event in events {
+            match event {
+                JournalEvent::Queued(entry) => if !seen.contains(&entry.url) && !rejected.contains(&entry.url) {
+                    if !pending.contains_key(&entry.url) {
+                        order.push(entry.url.clone());
+                    }
+                    pending.insert(entry.url.clone(), entry);
+                },
+                JournalEvent::Done(link) => {
+                    pending.remove(&link);
+                    if seen.insert(link.clone()) {
+                        processed.push(link);
+                    }
+                }
+                JournalEvent::Rejected(link) => {
+                    pending.remove(&link);
+                    rejected.insert(link);
+                }
+            }
+        }
+        Checkpoint {
+            seeds: self.seeds,
+            processed,
+            pending: order.into_iter().filter_map(|link| pending.remove(&link)).collect(),
+            spilled: self.spilled,
+            rejected: rejected.into_iter().collect(),
+        }
+    }
+}
+
+/// Keeps a crawl resumable with a snapshot of its state and a journal of every change since.
+/// Every change is appended to the journal as it happens, so nothing but pages being visited
+/// at the time is lost when the crawl is killed. Once the journal holds `snapshot_every`
+/// changes, a new snapshot replaces it.
+pub struct CheckpointStore {
+    directory: PathBuf,
+    seeds: Vec<String>,
+    journal: Mutex<File>,
+    events: AtomicUsize,
+    snapshot_every: usize,
+}
+
+impl CheckpointStore {
+    /// Starts a new checkpoint in the directory, replacing any there.
+    pub fn create(directory: &str, seeds: &[String]) -> Result<CheckpointStore, CheckpointError> {
+        fs::create_dir_all(directory)?;
+        let directory = PathBuf::from(directory);
+        write_snapshot(&directory, &Checkpoint { seeds: seeds.to_vec(), ..Checkpoint::default() })?;
+        CheckpointStore::open(directory, seeds.to_vec())
+    }
+
+    /// Loads the checkpoint in the directory, replaying the journal onto the snapshot, and
+    /// keeps checkpointing the resumed crawl there.
+    pub fn resume(directory: &str) -> Result<(CheckpointStore, Checkpoint), CheckpointError> {
+        let directory = PathBuf::from(directory);
+        let snapshot: Checkpoint = serde_json::from_reader(BufReader::new(File::open(directory.join(SNAPSHOT_FILE))?))?;
+        let checkpoint = snapshot.replay(read_journal(&directory.join(JOURNAL_FILE))?);
+        write_snapshot(&directory, &checkpoint)?;
+        let store = CheckpointStore::open(directory, checkpoint.seeds.clone())?;
+        Ok((store, checkpoint))
+    }
+
+    fn open(directory: PathBuf, seeds: Vec<String>) -> Result<CheckpointStore, CheckpointError> {
+        let journal = truncated_journal(&directory)?;
+        Ok(CheckpointStore {
+            directory,
+            seeds,
+            journal: Mutex::new(journal),
+            events: AtomicUsize::new(0),
+            snapshot_every: DEFAULT_SNAPSHOT_EVERY,
+        })
+    }
+
+    /// Appends the change to the journal, and replaces the journal with a snapshot of the
+    /// state given by `state` when it has grown long enough, returning whether it did.
+    /// Changes must be made to the state before they are recorded, so that a snapshot never
+    /// misses one.
+    pub fn record<F: FnOnce() -> Checkpoint>(&self, event: JournalEvent, state: F) -> bool {
+        let mut journal = self.journal.lock().unwrap();
+        if let Err(err) = append(&mut journal, &event) {
+            log::warn!("Unable to write to the checkpoint journal: {}", err);
+            return false;
+        }
+        if self.events.fetch_add(1, Ordering::SeqCst) + 1 < self.snapshot_every {
+            return false;
+        }
+        let checkpoint = Checkpoint { seeds: self.seeds.clone(), ..state() };
+        match write_snapshot(&self.directory, &checkpoint).and_then(|_| truncated_journal(&self.directory)) {
+            Ok(truncated) => {
+                *journal = truncated;
+                self.events.store(0, Ordering::SeqCst);
+                true
+            }
+            Err(err) => {
+                log::warn!("Unable to write a checkpoint: {}", err);
+                false
+            }
+        }
+    }
+}
+
+fn append(journal: &mut File, event: &JournalEvent) -> Result<(), CheckpointError> {
+    let line = format!("{}\n", serde_json::to_string(event)?);
+    journal.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+fn read_journal(path: &Path) -> Result<Vec<JournalEvent>, CheckpointError> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let mut events = vec![];
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        match serde_json::from_str(&line) {
+            Ok(event) => events.push(event),
+            Err(err) => log::warn!("Ignoring unreadable journal entry {:?}: {}", line, err),
+        }
+    }
+    Ok(events)
+}
+
+/// Writes the snapshot next to the old one and moves it in place, so that a crawl killed
+/// while writing keeps the old snapshot.
+fn write_snapshot(directory: &Path, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+    let temporary = directory.join(format!("{}.tmp", SNAPSHOT_FILE));
+    fs::write(&temporary, serde_json::to_vec(checkpoint)?)?;
+    fs::rename(&temporary, directory.join(SNAPSHOT_FILE))?;
+    Ok(())
+}
+
+fn truncated_journal(directory: &Path) -> Result<File, CheckpointError> {
+    Ok(OpenOptions::new().create(true).write(true).truncate(true).open(directory.join(JOURNAL_FILE))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::queue::checkpoint::{Checkpoint, CheckpointStore, JOURNAL_FILE, JournalEvent};
+    use crate::queue::frontier::FrontierEntry;
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("crawly-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    #[test]
+    fn replay_should_apply_journal_events_to_the_snapshot() {
+        let snapshot = Checkpoint {
+            seeds: vec!["https://domain.com/".to_string()],
+            processed: vec!["https://domain.com/".to_string()],
+            pending: vec![FrontierEntry::seed("https://domain.com/a"), FrontierEntry::seed("https://domain.com/b")],
+            spilled: vec![],
+            rejected: vec![],
+        };
+
+        let checkpoint = snapshot.replay(vec![
+            JournalEvent::Done("https://domain.com/a".to_string()),
+            JournalEvent::Queued(FrontierEntry::seed("https://domain.com/c")),
+            JournalEvent::Queued(FrontierEntry::seed("https://domain.com/a")),
+            JournalEvent::Queued(FrontierEntry::seed("https://domain.com/d")),
+            JournalEvent::Rejected("https://domain.com/d".to_string()),
+        ]);
+
+        assert_eq!(checkpoint, Checkpoint {
+            seeds: vec!["https://domain.com/".to_string()],
+            processed: vec!["https://domain.com/".to_string(), "https://domain.com/a".to_string()],
+            pending: vec![FrontierEntry::seed("https://domain.com/b"), FrontierEntry::seed("https://domain.com/c")],
+            spilled: vec![],
+            rejected: vec!["https://domain.com/d".to_string()],
+        })
+    }
+
+    #[test]
+    fn should_resume_from_the_snapshot_and_journal() {
+        let directory = directory("resume");
+        let seeds = vec!["https://domain.com/".to_string()];
+        let store = CheckpointStore::create(directory.to_str().unwrap(), &seeds).unwrap();
+        store.record(JournalEvent::Queued(FrontierEntry::seed("https://domain.com/")), Checkpoint::default);
+        store.record(JournalEvent::Done("https://domain.com/".to_string()), Checkpoint::default);
+        store.record(JournalEvent::Queued(FrontierEntry::seed("https://domain.com/a")), Checkpoint::default);
+        drop(store);
+
+        let (_, checkpoint) = CheckpointStore::resume(directory.to_str().unwrap()).unwrap();
+
+        assert_eq!(checkpoint.seeds, seeds);
+        assert_eq!(checkpoint.processed, vec!["https://domain.com/"]);
+        assert_eq!(checkpoint.pending, vec![FrontierEntry::seed("https://domain.com/a")]);
+        assert_eq!(fs::read_to_string(directory.join(JOURNAL_FILE)).unwrap(), "");
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn should_replace_the_journal_with_a_snapshot_once_it_is_long_enough() {
+        let directory = directory("snapshot");
+        let mut store = CheckpointStore::create(directory.to_str().unwrap(), &[]).unwrap();
+        store.snapshot_every = 2;
+        let state = || Checkpoint { processed: vec!["https://domain.com/".to_string()], ..Checkpoint::default() };
+
+        assert!(!store.record(JournalEvent::Queued(FrontierEntry::seed("https://domain.com/")), state));
+        assert!(store.record(JournalEvent::Done("https://domain.com/".to_string()), state));
+        drop(store);
+
+        assert_eq!(fs::read_to_string(directory.join(JOURNAL_FILE)).unwrap(), "");
+        let (_, checkpoint) = CheckpointStore::resume(directory.to_str().unwrap()).unwrap();
+        assert_eq!(checkpoint.processed, vec!["https://domain.com/"]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+use std::str::FromStr;
+
+use hyper::{Body, Method, Request, Uri};
+use hyper::header;
+use url::Url;
+
+use crate::client::HttpClient;
+use crate::queue::async_decider::AsyncQueueAdditionDecider;
+use crate::queue::queue_addition_decider::Decision;
+
+pub const CONTENT_TYPE: &str = "content-type";
+const PAGE_TYPES: &[&str] = &["text/html", "application/xhtml+xml"];
+
+/// Asks the server for the content type of links whose extension does not give away that
+/// they are pages, with a HEAD request, and turns down links to anything else. Links that
+/// cannot be checked are left for the crawl to find out.
+pub struct ContentTypeDecider {
+    client: HttpClient,
+}
+
+impl ContentTypeDecider {
+    pub fn new(client: HttpClient) -> ContentTypeDecider {
+        ContentTypeDecider { client }
+    }
+
+    fn looks_like_page(link: &str) -> bool {
+        Url::parse(link)
+            .ok()
+            .and_then(|url| mime_guess::from_path(url.path()).first())
+            .map(|mime| PAGE_TYPES.contains(&mime.essence_str()))
+            .unwrap_or(false)
+    }
+
+    async fn content_type(&self, link: &str) -> Option<String> {
+        let request = Request::builder()
+            .method(Method::HEAD)
+            .uri(Uri::from_str(link).ok()?)
+            .body(Body::empty())
+            .ok()?;
+        let response = self.client.request(request).await.ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        let content_type = response.headers().get(header::CONTENT_TYPE)?.to_str().ok()?;
+        Some(content_type.split(';').next().unwrap_or("").trim().to_lowercase())
+    }
+}
+
+#[async_trait]
+impl AsyncQueueAdditionDecider for ContentTypeDecider {
+    async fn decide(&self, link: &str) -> Decision {
+        if ContentTypeDecider::looks_like_page(link) {
+            return Decision::accept(CONTENT_TYPE, "extension is a page type");
+        }
+        match self.content_type(link).await {
+            Some(content_type) if PAGE_TYPES.contains(&content_type.as_str()) =>
+                Decision::accept(CONTENT_TYPE, format!("server reports {}", content_type)),
+            Some(content_type) =>
+                Decision::reject(CONTENT_TYPE, format!("server reports {}, not a page", content_type)),
+            None => Decision::accept(CONTENT_TYPE, "content type could not be checked"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use wiremock::matchers::{method, path};
+
+    use crate::client::{AddressPolicy, create_http_client};
+    use crate::queue::async_decider::AsyncQueueAdditionDecider;
+    use crate::queue::content_type_decider::ContentTypeDecider;
+    use crate::queue::queue_addition_decider::Decision;
+
+    #[tokio::test]
+    async fn should_accept_links_the_server_reports_as_pages() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD")).and(path("/docs"))
+            .respond_with(ResponseTemplate::new(200).insert_header("Content-Type", "text/html; charset=utf-8"))
+            .mount(&server).await;
+        let decider = ContentTypeDecider::new(create_http_client(AddressPolicy::AllowAll));
+
+        let decision = decider.decide(&format!("{}/docs", server.uri())).await;
+
+        assert_eq!(decision, Decision::accept("content-type", "server reports text/html"))
+    }
+
+    #[tokio::test]
+    async fn should_reject_links_to_other_content() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD")).and(path("/download"))
+            .respond_with(ResponseTemplate::new(200).insert_header("Content-Type", "application/zip"))
+            .mount(&server).await;
+        let decider = ContentTypeDecider::new(create_http_client(AddressPolicy::AllowAll));
+
+        let decision = decider.decide(&format!("{}/download", server.uri())).await;
+
+        assert_eq!(decision, Decision::reject("content-type", "server reports application/zip, not a page"))
+    }
+
+    #[tokio::test]
+    async fn should_not_ask_about_links_with_page_extensions() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .respond_with(ResponseTemplate::new(200).insert_header("Content-Type", "application/zip"))
+            .expect(0)
+            .mount(&server).await;
+        let decider = ContentTypeDecider::new(create_http_client(AddressPolicy::AllowAll));
+
+        let decision = decider.decide(&format!("{}/index.html", server.uri())).await;
+
+        assert!(decision.accepted)
+    }
+}
@@ -1,56 +1,296 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use flurry::HashSet;
+use futures::future::join_all;
+
+use crate::queue::admission_observer::AdmissionObserver;
+use crate::queue::already_exists_decider::{ALREADY_SEEN, ItemAlreadyExistsDecider};
+use crate::queue::async_decider::AsyncQueueAdditionDecider;
+use crate::queue::bloom_filter::BloomFilterSettings;
+use crate::queue::checkpoint::{Checkpoint, CheckpointStore, JournalEvent};
+use crate::queue::decider_combinators::AllOf;
+use crate::queue::frontier::{CrawlStrategy, Frontier, FrontierEntry};
+use crate::queue::frontier_segments::FrontierSegments;
+use crate::queue::queue_addition_decider::{Decision, QueueAdditionDecider};
+use crate::queue::rejection_log::RejectionLog;
+use crate::queue::seen_set::SeenSet;
+
+pub const DEFAULT_BATCH_SIZE: usize = 25;
+
+#[derive(Default)]
+pub struct QueueSettings {
+    pub strategy: CrawlStrategy,
+    pub max_pages: Option<usize>,
+    pub batch_size: Option<usize>,
+    pub audit_log: Option<Arc<RejectionLog>>,
+    pub checkpoint: Option<Arc<CheckpointStore>>,
+    /// Looks up visited pages in a Bloom filter, keeping their links only for checkpoints.
+    /// Queued and rejected links are kept in full.
+    pub bloom_filter: Option<BloomFilterSettings>,
+    pub frontier_segments: Option<FrontierSegments>,
+}
+
+/// What became of the entries offered to the queue.
+#[derive(Debug, Default)]
+pub struct Admission {
+    pub queued: Vec<FrontierEntry>,
+    /// The entries a decider turned down, with its decision. Links seen before are left out.
+    pub rejected: Vec<(FrontierEntry, Decision)>,
+}
 
-use crate::queue::already_exists_decider::ItemAlreadyExistsDecider;
-use crate::queue::queue_addition_decider::QueueAdditionDecider;
+impl Admission {
+    fn reject(&mut self, entry: FrontierEntry, decision: Decision) {
+        if decision.decider != ALREADY_SEEN {
+            self.rejected.push((entry, decision));
+        }
+    }
+}
 
 pub struct CrawlQueue {
-    deciders: Vec<Box<dyn QueueAdditionDecider>>,
-    processed: HashSet<String>,
+    deciders: AllOf,
+    async_deciders: Vec<Box<dyn AsyncQueueAdditionDecider>>,
+    observers: Vec<Arc<dyn AdmissionObserver>>,
+    processed: SeenSet,
     queue: HashSet<String>,
+    rejected: HashSet<String>,
+    frontier: Frontier,
+    max_pages: Option<usize>,
+    batch_size: usize,
+    dispatched: AtomicUsize,
+    in_flight: Mutex<HashMap<String, FrontierEntry>>,
+    audit_log: Option<Arc<RejectionLog>>,
+    checkpoint: Option<Arc<CheckpointStore>>,
 }
 
 impl CrawlQueue {
+    #[cfg(test)]
     pub fn new(deciders: Vec<Box<dyn QueueAdditionDecider>>) -> CrawlQueue {
-        let processed = HashSet::new();
+        CrawlQueue::with_settings(deciders, QueueSettings::default())
+    }
+
+    pub fn with_settings(deciders: Vec<Box<dyn QueueAdditionDecider>>, settings: QueueSettings) -> CrawlQueue {
+        let processed = SeenSet::new(settings.bloom_filter, settings.checkpoint.is_some());
         let queue = HashSet::new();
+        let frontier = match settings.frontier_segments {
+            Some(segments) => Frontier::new(settings.strategy).with_segments(segments),
+            None => Frontier::new(settings.strategy),
+        };
         CrawlQueue {
-            deciders,
+            deciders: AllOf::new("scope", deciders),
+            async_deciders: vec![],
+            observers: vec![],
             processed,
             queue,
+            rejected: HashSet::new(),
+            frontier,
+            max_pages: settings.max_pages,
+            batch_size: settings.batch_size.unwrap_or(DEFAULT_BATCH_SIZE),
+            dispatched: AtomicUsize::new(0),
+            in_flight: Mutex::new(HashMap::new()),
+            audit_log: settings.audit_log,
+            checkpoint: settings.checkpoint,
         }
     }
 
-    pub fn add_all(&self, links: Vec<String>) -> Vec<String> {
-        links.iter()
-            .filter_map(|link| if self.add_to_queue(link) {
-                Some(link.clone())
-            } else { None })
+    /// Adds deciders run after the sync ones, in order, for links that were not seen before.
+    pub fn with_async_deciders(mut self, deciders: Vec<Box<dyn AsyncQueueAdditionDecider>>) -> CrawlQueue {
+        self.async_deciders = deciders;
+        self
+    }
+
+    /// Adds observers told about the links the queue takes in or turns down.
+    pub fn with_observers(mut self, observers: Vec<Arc<dyn AdmissionObserver>>) -> CrawlQueue {
+        self.observers = observers;
+        self
+    }
+
+    pub async fn add_all(&self, links: Vec<String>) -> Vec<String> {
+        self.add_entries(links.iter().map(|link| FrontierEntry::seed(link)).collect()).await
+            .queued
+            .into_iter()
+            .map(|entry| entry.url)
             .collect()
     }
 
-    pub fn add_to_queue(&self, link: &str) -> bool {
-        if self.deciders.iter().any(|decider| !decider.can_add_to_queue(link)) {
-            return false;
+    /// Queues the entries the deciders accept. Links are reserved in the queue before the
+    /// async deciders run, so a link found twice while it is being checked is only checked
+    /// and queued once.
+    pub async fn add_entries(&self, entries: Vec<FrontierEntry>) -> Admission {
+        let mut admission = Admission::default();
+        let mut reserved = vec![];
+        for entry in entries {
+            match self.reserve(&entry.url) {
+                Ok(()) => reserved.push(entry),
+                Err(decision) => admission.reject(entry, decision),
+            }
+        }
+        let admitted = join_all(reserved.iter().map(|entry| self.admit(&entry.url))).await;
+        for (entry, admitted) in reserved.into_iter().zip(admitted) {
+            match admitted {
+                Ok(()) => {
+                    self.frontier.push(entry.clone());
+                    self.record(JournalEvent::Queued(entry.clone()));
+                    admission.queued.push(entry);
+                }
+                Err(decision) => admission.reject(entry, decision),
+            }
+        }
+        admission
+    }
+
+    #[cfg(test)]
+    pub async fn add_to_queue(&self, link: &str) -> bool {
+        !self.add_all(vec![link.to_string()]).await.is_empty()
+    }
+
+    /// Runs the link past every sync decider, returning the first rejection or an acceptance
+    /// when the link can be queued.
+    pub fn decide(&self, link: &str) -> Decision {
+        let decision = self.deciders.decide(link);
+        if !decision.accepted {
+            return decision;
+        }
+        if self.rejected.contains(link, &self.rejected.guard()) {
+            return Decision::reject(ALREADY_SEEN, "already rejected");
+        }
+        let seen = ItemAlreadyExistsDecider::new(&self.queue, &self.processed).decide(link);
+        if !seen.accepted {
+            return seen;
+        }
+        decision
+    }
+
+    fn reserve(&self, link: &str) -> Result<(), Decision> {
+        let decision = self.decide(link);
+        if !decision.accepted {
+            self.record_rejection(link, &decision);
+            return Err(decision);
+        }
+        if self.queue.insert(link.to_string(), &self.queue.guard()) {
+            self.observers.iter().for_each(|observer| observer.queued(link));
+            Ok(())
+        } else {
+            Err(Decision::reject(ALREADY_SEEN, "already queued"))
+        }
+    }
+
+    async fn admit(&self, link: &str) -> Result<(), Decision> {
+        for decider in &self.async_deciders {
+            let decision = decider.decide(link).await;
+            if !decision.accepted {
+                self.record_rejection(link, &decision);
+                self.rejected.insert(link.to_string(), &self.rejected.guard());
+                self.queue.remove(link, &self.queue.guard());
+                self.record(JournalEvent::Rejected(link.to_string()));
+                return Err(decision);
+            }
+        }
+        Ok(())
+    }
+
+    fn record_rejection(&self, link: &str, decision: &Decision) {
+        log::debug!("Skipping {}: rejected by {}, {}", link, decision.decider, decision.reason);
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.record(link, decision);
+        }
+        if decision.decider != ALREADY_SEEN {
+            self.observers.iter().for_each(|observer| observer.rejected(link, decision));
+        }
+    }
+
+    /// Takes the next links to visit off the frontier, in the order of the crawl strategy,
+    /// until the batch is full or the page budget is spent. Entries of spilled segments that
+    /// were visited or rejected since the segment was written are passed over.
+    pub fn next_batch(&self) -> Vec<FrontierEntry> {
+        let mut batch = vec![];
+        while batch.len() < self.batch_size && !self.budget_exhausted() {
+            match self.frontier.pop() {
+                Some(entry) if !self.queue.contains(&entry.url, &self.queue.guard()) => continue,
+                Some(entry) => {
+                    self.dispatched.fetch_add(1, Ordering::SeqCst);
+                    self.in_flight.lock().unwrap().insert(entry.url.clone(), entry.clone());
+                    batch.push(entry);
+                }
+                None => break
+            }
+        }
+        if self.checkpoint.is_none() {
+            self.frontier.remove_loaded_segments();
         }
-        let default_decider = ItemAlreadyExistsDecider::new(&self.queue, &self.processed);
-        if !default_decider.can_add_to_queue(link) {
-            return false;
+        batch
+    }
+
+    /// Decides on the page a visited link redirected to with the sync deciders. A page in
+    /// scope is marked as done, so that links to it are not visited again.
+    pub fn redirected(&self, link: &str) -> Decision {
+        let decision = self.deciders.decide(link);
+        if decision.accepted {
+            self.mark_as_done(link);
+        } else {
+            self.record_rejection(link, &decision);
         }
-        self.queue.insert(link.to_string(), &self.queue.guard())
+        decision
     }
 
     pub fn mark_as_done(&self, link: &str) {
         let queue_guard = self.queue.guard();
-        let processed_guard = self.processed.guard();
         self.queue.remove(link, &queue_guard);
-        self.processed.insert(link.to_string(), &processed_guard);
+        self.processed.insert(link);
+        self.in_flight.lock().unwrap().remove(link);
+        self.record(JournalEvent::Done(link.to_string()));
+    }
+
+    /// Picks up a crawl from a checkpoint: its visited pages count as crawled and towards
+    /// the page budget, and the links it had yet to visit, or was visiting, are queued. The
+    /// frontier must have been given the checkpoint's spilled segments already.
+    pub fn restore(&self, checkpoint: Checkpoint) {
+        for link in checkpoint.processed {
+            self.processed.insert(&link);
+        }
+        for link in checkpoint.rejected {
+            self.rejected.insert(link, &self.rejected.guard());
+        }
+        for link in self.frontier.spilled_links() {
+            if !self.processed.contains(&link) && !self.rejected.contains(&link, &self.rejected.guard()) {
+                self.queue.insert(link, &self.queue.guard());
+            }
+        }
+        for entry in checkpoint.pending {
+            if self.queue.insert(entry.url.clone(), &self.queue.guard()) {
+                self.frontier.push(entry);
+            }
+        }
+        self.dispatched.store(self.processed.len(), Ordering::SeqCst);
+    }
+
+    fn record(&self, event: JournalEvent) {
+        if let Some(store) = &self.checkpoint {
+            if store.record(event, || self.checkpoint()) {
+                self.frontier.remove_loaded_segments();
+            }
+        }
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        let mut pending: Vec<FrontierEntry> = self.in_flight.lock().unwrap().values().cloned().collect();
+        pending.extend(self.frontier.entries());
+        Checkpoint {
+            seeds: vec![],
+            processed: self.finished(),
+            pending,
+            spilled: self.frontier.segments(),
+            rejected: self.rejected.iter(&self.rejected.guard()).cloned().collect(),
+        }
     }
 
+    /// The visited pages. With a Bloom filter, they are only kept for checkpoints.
     pub fn finished(&self) -> Vec<String> {
-        let finished_guard = self.processed.guard();
-        self.processed.iter(&finished_guard).cloned().collect()
+        self.processed.links()
     }
 
+    #[cfg(test)]
     pub fn items(&self) -> Vec<String> {
         let queue_guard = self.queue.guard();
         self.queue.iter(&queue_guard).cloned().collect()
@@ -59,13 +299,38 @@ impl CrawlQueue {
     pub fn is_empty(&self) -> bool {
         self.queue.is_empty()
     }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.is_empty() || self.budget_exhausted()
+    }
+
+    fn budget_exhausted(&self) -> bool {
+        self.max_pages
+            .map(|max_pages| self.dispatched.load(Ordering::SeqCst) >= max_pages)
+            .unwrap_or(false)
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::map_clone)]
 mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::AtomicUsize;
+
     use flurry::HashSet;
 
-    use crate::queue::CrawlQueue;
+    use crate::queue::{CrawlQueue, QueueSettings};
+    use mockall::predicate::eq;
+
+    use crate::queue::admission_observer::MockAdmissionObserver;
+    use crate::queue::async_decider::MockAsyncQueueAdditionDecider;
+    use crate::queue::checkpoint::Checkpoint;
+    use crate::queue::decider_combinators::AllOf;
+    use crate::queue::frontier::{CrawlStrategy, Frontier, FrontierEntry};
+    use crate::queue::frontier_segments::FrontierSegments;
+    use crate::queue::queue_addition_decider::Decision;
+    use crate::queue::seen_set::SeenSet;
 
     fn hash_set_to_vec(set: HashSet<String>) -> Vec<String> {
         set.iter(&set.guard()).map(|item| item.clone()).collect::<Vec<String>>()
@@ -78,80 +343,230 @@ mod tests {
         set
     }
 
-    #[test]
-    fn should_add_item_to_queue() {
+    fn crawl_queue(finished: HashSet<String>, queue: HashSet<String>) -> CrawlQueue {
+        let processed = SeenSet::default();
+        finished.iter(&finished.guard()).for_each(|link| processed.insert(link));
+        CrawlQueue {
+            deciders: AllOf::new("scope", vec![]),
+            async_deciders: vec![],
+            observers: vec![],
+            processed,
+            queue,
+            rejected: HashSet::new(),
+            frontier: Frontier::new(CrawlStrategy::BreadthFirst),
+            max_pages: None,
+            batch_size: 25,
+            dispatched: AtomicUsize::new(0),
+            in_flight: Mutex::new(HashMap::new()),
+            audit_log: None,
+            checkpoint: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn should_add_item_to_queue() {
         let queue = CrawlQueue::new(vec![]);
 
-        let added_items = queue.add_to_queue("https://domain.com");
+        let added_items = queue.add_to_queue("https://domain.com").await;
 
         assert!(added_items);
         assert_eq!(hash_set_to_vec(queue.queue), vec!["https://domain.com"])
     }
 
-    #[test]
-    fn should_not_add_item_to_queue_when_already_in_queue() {
-        let queue = CrawlQueue {
-            deciders: vec![],
-            processed: HashSet::new(),
-            queue: vec_to_hash_set(vec!["https://domain.com"]),
-        };
+    #[tokio::test]
+    async fn should_not_add_item_to_queue_when_already_in_queue() {
+        let queue = crawl_queue(HashSet::new(), vec_to_hash_set(vec!["https://domain.com"]));
 
-        let added_items = queue.add_to_queue("https://domain.com");
+        let added_items = queue.add_to_queue("https://domain.com").await;
 
         assert!(!added_items);
         assert_eq!(hash_set_to_vec(queue.queue), vec!["https://domain.com"])
     }
 
-    #[test]
-    fn should_not_add_item_to_queue_when_already_processed() {
-        let queue = CrawlQueue {
-            deciders: vec![],
-            processed: vec_to_hash_set(vec!["https://domain.com"]),
-            queue: HashSet::new(),
-        };
+    #[tokio::test]
+    async fn should_not_add_item_to_queue_when_already_processed() {
+        let queue = crawl_queue(vec_to_hash_set(vec!["https://domain.com"]), HashSet::new());
 
-        let added_items = queue.add_to_queue("https://domain.com");
+        let added_items = queue.add_to_queue("https://domain.com").await;
 
         assert!(!added_items);
         assert_eq!(hash_set_to_vec(queue.queue), Vec::<&str>::new())
     }
 
     #[test]
-    fn add_all_should_add_items_and_return_the_items_that_are_added() {
-        let queue = CrawlQueue {
-            deciders: vec![],
-            processed: HashSet::new(),
-            queue: vec_to_hash_set(vec!["https://domain.com"]),
-        };
+    fn decide_should_explain_why_a_link_cannot_be_queued() {
+        let queue = crawl_queue(vec_to_hash_set(vec!["https://domain.com"]), HashSet::new());
+
+        let decision = queue.decide("https://domain.com");
+
+        assert_eq!(decision, Decision::reject("already-seen", "already crawled"))
+    }
+
+    #[tokio::test]
+    async fn should_not_add_item_when_an_async_decider_rejects_it() {
+        let mut decider = MockAsyncQueueAdditionDecider::new();
+        decider.expect_decide()
+            .with(eq("https://domain.com/download"))
+            .times(1)
+            .returning(|_| Decision::reject("mock", "not a page"));
+        decider.expect_decide()
+            .with(eq("https://domain.com/page"))
+            .returning(|_| Decision::accept("mock", "a page"));
+        let queue = CrawlQueue::new(vec![]).with_async_deciders(vec![Box::new(decider)]);
+
+        let added = queue.add_all(vec!["https://domain.com/download".to_string(), "https://domain.com/page".to_string()]).await;
+        let added_again = queue.add_all(vec!["https://domain.com/download".to_string()]).await;
+
+        assert_eq!(added, vec!["https://domain.com/page"]);
+        assert_eq!(added_again, Vec::<String>::new());
+        assert_eq!(queue.items(), vec!["https://domain.com/page"]);
+        assert_eq!(queue.decide("https://domain.com/download"), Decision::reject("already-seen", "already rejected"))
+    }
+
+    #[tokio::test]
+    async fn should_report_rejected_entries_with_the_decision_but_not_seen_ones() {
+        let mut decider = MockAsyncQueueAdditionDecider::new();
+        decider.expect_decide()
+            .with(eq("https://domain.com/download"))
+            .returning(|_| Decision::reject("mock", "not a page"));
+        decider.expect_decide()
+            .returning(|_| Decision::accept("mock", "a page"));
+        let queue = CrawlQueue::new(vec![]).with_async_deciders(vec![Box::new(decider)]);
+        let seed = FrontierEntry::seed("https://domain.com/");
+
+        let admission = queue.add_entries(vec![
+            FrontierEntry::discovered("https://domain.com/download", &seed, "download"),
+            FrontierEntry::discovered("https://domain.com/page", &seed, "page"),
+            FrontierEntry::discovered("https://domain.com/page", &seed, "page again"),
+        ]).await;
+
+        assert_eq!(admission.queued.into_iter().map(|entry| entry.url).collect::<Vec<String>>(), vec!["https://domain.com/page"]);
+        assert_eq!(admission.rejected.len(), 1);
+        assert_eq!(admission.rejected[0].0.url, "https://domain.com/download");
+        assert_eq!(admission.rejected[0].1, Decision::reject("mock", "not a page"))
+    }
+
+    #[tokio::test]
+    async fn should_tell_observers_about_links_found_for_the_first_time() {
+        let mut decider = MockAsyncQueueAdditionDecider::new();
+        decider.expect_decide()
+            .with(eq("https://domain.com/download"))
+            .returning(|_| Decision::reject("mock", "not a page"));
+        decider.expect_decide()
+            .returning(|_| Decision::accept("mock", "a page"));
+        let mut observer = MockAdmissionObserver::new();
+        observer.expect_queued()
+            .with(eq("https://domain.com/page"))
+            .times(1)
+            .return_const(());
+        observer.expect_queued()
+            .with(eq("https://domain.com/download"))
+            .times(1)
+            .return_const(());
+        observer.expect_rejected()
+            .with(eq("https://domain.com/download"), eq(Decision::reject("mock", "not a page")))
+            .times(1)
+            .return_const(());
+        let queue = CrawlQueue::new(vec![])
+            .with_async_deciders(vec![Box::new(decider)])
+            .with_observers(vec![Arc::new(observer)]);
+
+        queue.add_all(vec!["https://domain.com/page".to_string(), "https://domain.com/download".to_string()]).await;
+        queue.add_all(vec!["https://domain.com/page".to_string(), "https://domain.com/download".to_string()]).await;
+        queue.decide("https://domain.com/other");
+    }
+
+    #[tokio::test]
+    async fn should_continue_from_a_restored_checkpoint() {
+        let queue = CrawlQueue::with_settings(vec![], QueueSettings { max_pages: Some(3), ..QueueSettings::default() });
+
+        queue.restore(Checkpoint {
+            seeds: vec![],
+            processed: vec!["https://domain.com/".to_string()],
+            pending: vec![FrontierEntry { url: "https://domain.com/a".to_string(), depth: 1, anchor_text: "a".to_string(), referrer: Some("https://domain.com/".to_string()) }],
+            spilled: vec![],
+            rejected: vec!["https://domain.com/download".to_string()],
+        });
+
+        assert!(!queue.add_to_queue("https://domain.com/").await);
+        assert!(!queue.add_to_queue("https://domain.com/download").await);
+        assert!(queue.add_to_queue("https://domain.com/b").await);
+        assert_eq!(queue.next_batch().into_iter().map(|entry| entry.url).collect::<Vec<String>>(), vec!["https://domain.com/b", "https://domain.com/a"]);
+        assert!(queue.is_exhausted())
+    }
+
+    #[tokio::test]
+    async fn should_resume_links_spilled_to_disk() {
+        let directory = std::env::temp_dir().join(format!("crawly-queue-spilled-{}", std::process::id()));
+        let segments = FrontierSegments::open(directory.to_str().unwrap(), 2, vec![]).unwrap();
+        let queue = CrawlQueue::with_settings(vec![], QueueSettings { frontier_segments: Some(segments), ..QueueSettings::default() });
+        let entries = (1..=5).rev()
+            .map(|depth| FrontierEntry { url: format!("https://domain.com/{}", depth), depth, anchor_text: String::new(), referrer: None })
+            .collect();
+        queue.add_entries(entries).await;
+        let checkpoint = queue.checkpoint();
+        assert!(!checkpoint.spilled.is_empty());
+        drop(queue);
+
+        let segments = FrontierSegments::open(directory.to_str().unwrap(), 2, checkpoint.spilled.clone()).unwrap();
+        let resumed = CrawlQueue::with_settings(vec![], QueueSettings { frontier_segments: Some(segments), ..QueueSettings::default() });
+        resumed.restore(Checkpoint { processed: vec!["https://domain.com/4".to_string()], ..checkpoint });
+
+        assert_eq!(resumed.next_batch().into_iter().map(|entry| entry.url).collect::<Vec<String>>(),
+                   vec!["https://domain.com/1", "https://domain.com/2", "https://domain.com/3", "https://domain.com/5"]);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn checkpoint_should_hold_pages_being_visited_as_pending() {
+        let queue = CrawlQueue::new(vec![]);
+        queue.add_all(vec!["https://domain.com/a".to_string(), "https://domain.com/b".to_string()]).await;
+        queue.next_batch();
+        queue.mark_as_done("https://domain.com/a");
+
+        let checkpoint = queue.checkpoint();
+
+        assert_eq!(checkpoint.processed, vec!["https://domain.com/a"]);
+        assert_eq!(checkpoint.pending, vec![FrontierEntry::seed("https://domain.com/b")])
+    }
+
+    #[tokio::test]
+    async fn should_check_a_link_found_twice_only_once() {
+        let mut decider = MockAsyncQueueAdditionDecider::new();
+        decider.expect_decide()
+            .times(1)
+            .returning(|_| Decision::accept("mock", "a page"));
+        let queue = CrawlQueue::new(vec![]).with_async_deciders(vec![Box::new(decider)]);
+
+        let added = queue.add_all(vec!["https://domain.com/page".to_string(), "https://domain.com/page".to_string()]).await;
+
+        assert_eq!(added, vec!["https://domain.com/page"])
+    }
+
+    #[tokio::test]
+    async fn add_all_should_add_items_and_return_the_items_that_are_added() {
+        let queue = crawl_queue(HashSet::new(), vec_to_hash_set(vec!["https://domain.com"]));
 
         let added = queue.add_all(vec![
             "https://domain.com".to_string(),
-            "https://domain1.com".to_string()]);
+            "https://domain1.com".to_string()]).await;
 
         assert_eq!(added, vec!["https://domain1.com"])
     }
 
     #[test]
     fn should_move_item_from_queue_to_processed_when_marked_as_done() {
-        let queue = CrawlQueue {
-            deciders: vec![],
-            processed: HashSet::new(),
-            queue: vec_to_hash_set(vec!["https://domain.com"]),
-        };
+        let queue = crawl_queue(HashSet::new(), vec_to_hash_set(vec!["https://domain.com"]));
 
         queue.mark_as_done("https://domain.com");
 
-        assert_eq!(hash_set_to_vec(queue.processed), vec!["https://domain.com"]);
+        assert_eq!(queue.processed.links(), vec!["https://domain.com"]);
         assert_eq!(hash_set_to_vec(queue.queue), Vec::<&str>::new());
     }
 
     #[test]
     fn finished_should_return_all_items_in_processed() {
-        let queue = CrawlQueue {
-            deciders: vec![],
-            processed: vec_to_hash_set(vec!["https://processed.com"]),
-            queue: vec_to_hash_set(vec!["https://queue.com"]),
-        };
+        let queue = crawl_queue(vec_to_hash_set(vec!["https://processed.com"]), vec_to_hash_set(vec!["https://queue.com"]));
 
         let finished = queue.finished();
 
@@ -160,11 +575,7 @@ mod tests {
 
     #[test]
     fn items_should_return_all_items_in_queue() {
-        let queue = CrawlQueue {
-            deciders: vec![],
-            processed: vec_to_hash_set(vec!["https://processed.com"]),
-            queue: vec_to_hash_set(vec!["https://queue.com"]),
-        };
+        let queue = crawl_queue(vec_to_hash_set(vec!["https://processed.com"]), vec_to_hash_set(vec!["https://queue.com"]));
 
         let finished = queue.items();
 
@@ -173,11 +584,7 @@ mod tests {
 
     #[test]
     fn is_empty_should_return_false_when_queue_is_not_empty() {
-        let queue = CrawlQueue {
-            deciders: vec![],
-            processed: vec_to_hash_set(vec!["https://processed.com"]),
-            queue: vec_to_hash_set(vec!["https://queue.com", "https://queue2.com"]),
-        };
+        let queue = crawl_queue(vec_to_hash_set(vec!["https://processed.com"]), vec_to_hash_set(vec!["https://queue.com", "https://queue2.com"]));
 
         let is_empty = queue.is_empty();
 
@@ -186,14 +593,47 @@ mod tests {
 
     #[test]
     fn is_empty_should_return_true_when_queue_is_empty() {
-        let queue = CrawlQueue {
-            deciders: vec![],
-            processed: vec_to_hash_set(vec!["https://processed.com"]),
-            queue: vec_to_hash_set(vec![]),
-        };
+        let queue = crawl_queue(vec_to_hash_set(vec!["https://processed.com"]), vec_to_hash_set(vec![]));
 
         let is_empty = queue.is_empty();
 
         assert_eq!(is_empty, true)
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn next_batch_should_return_queued_items_in_breadth_first_order() {
+        let queue = CrawlQueue::new(vec![]);
+        queue.add_to_queue("https://domain.com/1").await;
+        queue.add_to_queue("https://domain.com/2").await;
+
+        let batch = queue.next_batch();
+
+        assert_eq!(batch.iter().map(|entry| entry.url.as_str()).collect::<Vec<&str>>(), vec!["https://domain.com/1", "https://domain.com/2"]);
+        assert!(queue.next_batch().is_empty());
+    }
+
+    #[tokio::test]
+    async fn next_batch_should_not_exceed_batch_size() {
+        let queue = CrawlQueue::with_settings(vec![], QueueSettings { batch_size: Some(1), ..QueueSettings::default() });
+        queue.add_to_queue("https://domain.com/1").await;
+        queue.add_to_queue("https://domain.com/2").await;
+
+        let batch = queue.next_batch();
+
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0].url, "https://domain.com/1");
+    }
+
+    #[tokio::test]
+    async fn should_be_exhausted_when_page_budget_is_spent() {
+        let queue = CrawlQueue::with_settings(vec![], QueueSettings { max_pages: Some(1), ..QueueSettings::default() });
+        queue.add_to_queue("https://domain.com/1").await;
+        queue.add_to_queue("https://domain.com/2").await;
+
+        let batch = queue.next_batch();
+
+        assert_eq!(batch.len(), 1);
+        assert!(!queue.is_empty());
+        assert!(queue.is_exhausted());
+    }
+}
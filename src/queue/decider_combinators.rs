@@ -0,0 +1,131 @@
+use crate::queue::queue_addition_decider::{Decision, QueueAdditionDecider};
+
+/// Accepts a link when every decider accepts it, explaining the first rejection otherwise.
+pub struct AllOf {
+    name: String,
+    deciders: Vec<Box<dyn QueueAdditionDecider>>,
+}
+
+impl AllOf {
+    pub fn new(name: &str, deciders: Vec<Box<dyn QueueAdditionDecider>>) -> AllOf {
+        AllOf { name: name.to_string(), deciders }
+    }
+}
+
+impl QueueAdditionDecider for AllOf {
+    fn decide(&self, link: &str) -> Decision {
+        let mut accepted_by = vec![];
+        for decider in &self.deciders {
+            let decision = decider.decide(link);
+            if !decision.accepted {
+                return decision;
+            }
+            accepted_by.push(decision.decider);
+        }
+        Decision::accept(&self.name, format!("accepted by {}", accepted_by.join(", ")))
+    }
+}
+
+/// Accepts a link when at least one decider accepts it, explaining the first acceptance.
+pub struct AnyOf {
+    name: String,
+    deciders: Vec<Box<dyn QueueAdditionDecider>>,
+}
+
+impl AnyOf {
+    pub fn new(name: &str, deciders: Vec<Box<dyn QueueAdditionDecider>>) -> AnyOf {
+        AnyOf { name: name.to_string(), deciders }
+    }
+}
+
+impl QueueAdditionDecider for AnyOf {
+    fn decide(&self, link: &str) -> Decision {
+        let mut reasons = vec![];
+        for decider in &self.deciders {
+            let decision = decider.decide(link);
+            if decision.accepted {
+                return Decision::accept(&self.name, decision.reason);
+            }
+            reasons.push(decision.reason);
+        }
+        Decision::reject(&self.name, reasons.join("; "))
+    }
+}
+
+/// Inverts the decision of a decider.
+pub struct Not {
+    name: String,
+    decider: Box<dyn QueueAdditionDecider>,
+}
+
+impl Not {
+    pub fn new(name: &str, decider: Box<dyn QueueAdditionDecider>) -> Not {
+        Not { name: name.to_string(), decider }
+    }
+}
+
+impl QueueAdditionDecider for Not {
+    fn decide(&self, link: &str) -> Decision {
+        let decision = self.decider.decide(link);
+        Decision {
+            accepted: !decision.accepted,
+            decider: self.name.clone(),
+            reason: decision.reason,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockall::predicate::eq;
+
+    use crate::queue::decider_combinators::{AllOf, AnyOf, Not};
+    use crate::queue::queue_addition_decider::{Decision, MockQueueAdditionDecider, QueueAdditionDecider};
+
+    fn decider(name: &'static str, accepted: bool) -> Box<dyn QueueAdditionDecider> {
+        let mut decider = MockQueueAdditionDecider::new();
+        decider.expect_decide()
+            .with(eq("https://example.com/"))
+            .returning(move |_| if accepted {
+                Decision::accept(name, format!("{} accepts", name))
+            } else {
+                Decision::reject(name, format!("{} rejects", name))
+            });
+        Box::new(decider)
+    }
+
+    #[test]
+    fn all_of_should_accept_when_every_decider_accepts() {
+        let all = AllOf::new("all", vec![decider("first", true), decider("second", true)]);
+
+        assert_eq!(all.decide("https://example.com/"), Decision::accept("all", "accepted by first, second"))
+    }
+
+    #[test]
+    fn all_of_should_explain_the_first_rejection() {
+        let all = AllOf::new("all", vec![decider("first", true), decider("second", false), decider("third", false)]);
+
+        assert_eq!(all.decide("https://example.com/"), Decision::reject("second", "second rejects"))
+    }
+
+    #[test]
+    fn any_of_should_accept_when_one_decider_accepts() {
+        let any = AnyOf::new("any", vec![decider("first", false), decider("second", true)]);
+
+        assert_eq!(any.decide("https://example.com/"), Decision::accept("any", "second accepts"))
+    }
+
+    #[test]
+    fn any_of_should_reject_with_every_reason_when_no_decider_accepts() {
+        let any = AnyOf::new("any", vec![decider("first", false), decider("second", false)]);
+
+        assert_eq!(any.decide("https://example.com/"), Decision::reject("any", "first rejects; second rejects"))
+    }
+
+    #[test]
+    fn not_should_invert_the_decision() {
+        let not = Not::new("not", decider("first", true));
+
+        assert_eq!(not.decide("https://example.com/"), Decision::reject("not", "first accepts"))
+    }
+}
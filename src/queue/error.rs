@@ -0,0 +1,63 @@
+use std::fmt::{Display, Formatter};
+use std::error::Error;
+use crate::link::LinkConstructionError;
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum QueueConfigurationError {
+    InvalidScoreRule(String),
+    InvalidPattern(String, String),
+    SuffixListError(String, String),
+    InvalidSeed(String),
+    InvalidBloomFilter(String),
+}
+
+impl Display for QueueConfigurationError {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> std::fmt::Result {
+        let display_string = match self {
+            QueueConfigurationError::InvalidScoreRule(rule) => format!("Invalid score rule '{}'. Should be in format of pattern=weight.", rule),
+            QueueConfigurationError::InvalidPattern(pattern, reason) => format!("Invalid pattern '{}': {}", pattern, reason),
+            QueueConfigurationError::SuffixListError(path, reason) => format!("Could not load public suffix list {}: {}", path, reason),
+            QueueConfigurationError::InvalidSeed(reason) => format!("Invalid seed{}", reason),
+            QueueConfigurationError::InvalidBloomFilter(reason) => format!("Invalid Bloom filter: {}", reason),
+        };
+        write!(fmt, "{}", display_string)
+    }
+}
+
+impl Error for QueueConfigurationError {}
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    IOError(String),
+    FormatError(String),
+}
+
+impl Display for CheckpointError {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> std::fmt::Result {
+        let display_string = match self {
+            CheckpointError::IOError(reason) => format!("Could not read or write the checkpoint: {}", reason),
+            CheckpointError::FormatError(reason) => format!("Checkpoint is not in the expected format: {}", reason),
+        };
+        write!(fmt, "{}", display_string)
+    }
+}
+
+impl Error for CheckpointError {}
+
+impl From<std::io::Error> for CheckpointError {
+    fn from(err: std::io::Error) -> Self {
+        CheckpointError::IOError(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for CheckpointError {
+    fn from(err: serde_json::Error) -> Self {
+        CheckpointError::FormatError(err.to_string())
+    }
+}
+
+impl From<LinkConstructionError> for QueueConfigurationError {
+    fn from(err: LinkConstructionError) -> Self {
+        QueueConfigurationError::InvalidSeed(err.to_string())
+    }
+}
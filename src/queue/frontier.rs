@@ -0,0 +1,413 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::queue::frontier_segments::{FrontierSegments, SegmentInfo};
+use crate::queue::link_scorer::LinkScorer;
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct FrontierEntry {
+    pub url: String,
+    pub depth: usize,
+    pub anchor_text: String,
+    #[serde(default)]
+    pub referrer: Option<String>,
+}
+
+impl FrontierEntry {
+    pub fn seed(url: &str) -> FrontierEntry {
+        FrontierEntry {
+            url: url.to_string(),
+            depth: 0,
+            anchor_text: String::new(),
+            referrer: None,
+        }
+    }
+
+    pub fn discovered(url: &str, parent: &FrontierEntry, anchor_text: &str) -> FrontierEntry {
+        FrontierEntry {
+            url: url.to_string(),
+            depth: parent.depth + 1,
+            anchor_text: anchor_text.to_string(),
+            referrer: Some(parent.url.clone()),
+        }
+    }
+}
+
+#[derive(Default)]
+#[allow(clippy::enum_variant_names)]
+pub enum CrawlStrategy {
+    #[default]
+    BreadthFirst,
+    DepthFirst,
+    BestFirst(Box<dyn LinkScorer>),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PrioritizedEntry {
+    pub priority: i64,
+    pub sequence: u64,
+    pub entry: FrontierEntry,
+}
+
+impl PrioritizedEntry {
+    fn rank(&self) -> (i64, u64) {
+        (self.priority, self.sequence)
+    }
+}
+
+/// Whether an entry of the first rank is taken before one of the second.
+fn outranks((priority, sequence): (i64, u64), (other_priority, other_sequence): (i64, u64)) -> bool {
+    priority > other_priority || (priority == other_priority && sequence < other_sequence)
+}
+
+impl Ord for PrioritizedEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for PrioritizedEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for PrioritizedEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PrioritizedEntry {}
+
+struct FrontierState {
+    hosts: HashMap<String, BinaryHeap<PrioritizedEntry>>,
+    rotation: VecDeque<String>,
+    sequence: u64,
+    in_memory: usize,
+    /// How far memory may go past the limit before spilling, after a segment was loaded or
+    /// could not be written.
+    allowance: usize,
+    segments: Option<FrontierSegments>,
+}
+
+impl FrontierState {
+    fn insert(&mut self, prioritized: PrioritizedEntry) {
+        let host = Frontier::host_of(&prioritized.entry.url);
+        if !self.hosts.contains_key(&host) {
+            self.rotation.push_back(host.clone());
+        }
+        self.hosts.entry(host).or_default().push(prioritized);
+        self.in_memory += 1;
+    }
+
+    fn best_in_memory(&self) -> Option<(i64, u64)> {
+        self.hosts.values()
+            .filter_map(|entries| entries.peek().map(PrioritizedEntry::rank))
+            .fold(None, |best, rank| match best {
+                Some(best) if !outranks(rank, best) => Some(best),
+                _ => Some(rank),
+            })
+    }
+
+    /// Moves the lower ranked half of the entries in memory to a new segment once there are
+    /// more than the memory limit and its allowance. Entries that cannot be written stay in
+    /// memory, and spilling is tried again once there are about twice as many.
+    fn spill_if_full(&mut self) {
+        let limit = match &self.segments {
+            Some(segments) if self.in_memory > segments.memory_limit() + self.allowance => segments.memory_limit(),
+            _ => return,
+        };
+        let mut entries: Vec<PrioritizedEntry> = self.hosts.drain().flat_map(|(_, entries)| entries.into_vec()).collect();
+        entries.sort_by(|a, b| b.cmp(a));
+        let spilled = entries.split_off(limit / 2);
+        let rotation: Vec<String> = self.rotation.drain(..).collect();
+        self.in_memory = 0;
+        entries.into_iter().for_each(|prioritized| self.insert(prioritized));
+        self.rotation = rotation.into_iter().filter(|host| self.hosts.contains_key(host)).collect();
+        let remaining = self.hosts.keys().filter(|host| !self.rotation.contains(host)).cloned().collect::<Vec<String>>();
+        self.rotation.extend(remaining);
+        match self.segments.as_mut().map(|segments| segments.spill(spilled)) {
+            Some(Err((err, spilled))) => {
+                log::warn!("Unable to spill the frontier to disk, keeping it in memory: {}", err);
+                spilled.into_iter().for_each(|prioritized| self.insert(prioritized));
+                self.allowance = self.in_memory;
+            }
+            _ => self.allowance = 0,
+        }
+    }
+
+    /// Loads the best spilled segment back when it holds an entry that is due before every
+    /// entry in memory. The segment may take memory past the limit by its own size, so that
+    /// loading it does not write another one right away.
+    fn load_if_due(&mut self) {
+        let spilled = match self.segments.as_ref().and_then(FrontierSegments::best) {
+            Some(spilled) => spilled,
+            None => return,
+        };
+        if self.best_in_memory().map(|best| outranks(best, spilled)).unwrap_or(false) {
+            return;
+        }
+        match self.segments.as_mut().map(FrontierSegments::load_best) {
+            Some(Ok(entries)) => {
+                self.allowance = self.allowance.max(entries.len());
+                entries.into_iter().for_each(|prioritized| self.insert(prioritized));
+            }
+            Some(Err(err)) => log::warn!("Unable to load a frontier segment, its links are skipped: {}", err),
+            None => {}
+        }
+        self.spill_if_full();
+    }
+}
+
+pub struct Frontier {
+    strategy: CrawlStrategy,
+    state: Mutex<FrontierState>,
+}
+
+impl Frontier {
+    pub fn new(strategy: CrawlStrategy) -> Frontier {
+        Frontier {
+            strategy,
+            state: Mutex::new(FrontierState {
+                hosts: HashMap::new(),
+                rotation: VecDeque::new(),
+                sequence: 0,
+                in_memory: 0,
+                allowance: 0,
+                segments: None,
+            }),
+        }
+    }
+
+    /// Spills entries over the memory limit of the segments to disk, and continues from
+    /// the segments already there.
+    pub fn with_segments(self, segments: FrontierSegments) -> Frontier {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.sequence = segments.last_sequence().map(|last| last + 1).unwrap_or(0);
+            state.segments = Some(segments);
+        }
+        self
+    }
+
+    pub fn push(&self, entry: FrontierEntry) {
+        let mut state = self.state.lock().unwrap();
+        let sequence = state.sequence;
+        state.sequence += 1;
+        let priority = self.priority(&entry, sequence);
+        state.insert(PrioritizedEntry { priority, sequence, entry });
+        state.spill_if_full();
+    }
+
+    /// Takes the best entry of the next host in the rotation, so that every host gets
+    /// its turn regardless of how many links it has queued.
+    pub fn pop(&self) -> Option<FrontierEntry> {
+        let mut state = self.state.lock().unwrap();
+        state.load_if_due();
+        let host = state.rotation.pop_front()?;
+        let entries = state.hosts.get_mut(&host)?;
+        let next = entries.pop().map(|prioritized| prioritized.entry);
+        if entries.is_empty() {
+            state.hosts.remove(&host);
+        } else {
+            state.rotation.push_back(host);
+        }
+        state.in_memory -= 1;
+        if state.segments.as_ref().map(|segments| state.in_memory <= segments.memory_limit()).unwrap_or(false) {
+            state.allowance = 0;
+        }
+        next
+    }
+
+    /// Every entry still in memory, in the order they were pushed.
+    pub fn entries(&self) -> Vec<FrontierEntry> {
+        let state = self.state.lock().unwrap();
+        let mut entries: Vec<&PrioritizedEntry> = state.hosts.values().flat_map(|entries| entries.iter()).collect();
+        entries.sort_by_key(|prioritized| prioritized.sequence);
+        entries.into_iter().map(|prioritized| prioritized.entry.clone()).collect()
+    }
+
+    /// The segments spilled to disk.
+    pub fn segments(&self) -> Vec<SegmentInfo> {
+        self.state.lock().unwrap().segments.as_ref().map(FrontierSegments::segments).unwrap_or_default()
+    }
+
+    /// The links of the entries spilled to disk.
+    pub fn spilled_links(&self) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        match state.segments.as_ref().map(FrontierSegments::links) {
+            Some(Ok(links)) => links,
+            Some(Err(err)) => {
+                log::warn!("Unable to read the frontier segments: {}", err);
+                vec![]
+            }
+            None => vec![],
+        }
+    }
+
+    /// Deletes the segments that were loaded back into memory.
+    pub fn remove_loaded_segments(&self) {
+        if let Some(segments) = self.state.lock().unwrap().segments.as_mut() {
+            segments.remove_retired();
+        }
+    }
+
+    fn host_of(link: &str) -> String {
+        Url::parse(link)
+            .map(|url| format!("{}:{}", url.host_str().unwrap_or(""), url.port_or_known_default().unwrap_or(0)))
+            .unwrap_or_default()
+    }
+
+    fn priority(&self, entry: &FrontierEntry, sequence: u64) -> i64 {
+        match &self.strategy {
+            CrawlStrategy::BreadthFirst => -(entry.depth as i64),
+            CrawlStrategy::DepthFirst => sequence as i64,
+            CrawlStrategy::BestFirst(scorer) => scorer.score(entry),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::queue::frontier::{CrawlStrategy, Frontier, FrontierEntry};
+    use crate::queue::frontier_segments::FrontierSegments;
+    use crate::queue::link_scorer::MockLinkScorer;
+
+    fn entry(url: &str, depth: usize) -> FrontierEntry {
+        FrontierEntry { url: url.to_string(), depth, anchor_text: String::new(), referrer: None }
+    }
+
+    fn drain(frontier: &Frontier) -> Vec<String> {
+        std::iter::from_fn(|| frontier.pop()).map(|entry| entry.url).collect()
+    }
+
+    #[test]
+    fn should_pop_shallow_entries_first_in_insertion_order_for_breadth_first() {
+        let frontier = Frontier::new(CrawlStrategy::BreadthFirst);
+        frontier.push(entry("https://domain.com/a/b", 2));
+        frontier.push(entry("https://domain.com/a", 1));
+        frontier.push(entry("https://domain.com/c", 1));
+
+        assert_eq!(drain(&frontier), vec!["https://domain.com/a", "https://domain.com/c", "https://domain.com/a/b"])
+    }
+
+    #[test]
+    fn should_pop_most_recent_entry_first_for_depth_first() {
+        let frontier = Frontier::new(CrawlStrategy::DepthFirst);
+        frontier.push(entry("https://domain.com/a", 1));
+        frontier.push(entry("https://domain.com/c", 1));
+        frontier.push(entry("https://domain.com/c/d", 2));
+
+        assert_eq!(drain(&frontier), vec!["https://domain.com/c/d", "https://domain.com/c", "https://domain.com/a"])
+    }
+
+    #[test]
+    fn should_pop_highest_scored_entry_first_for_best_first() {
+        let mut scorer = MockLinkScorer::new();
+        scorer.expect_score()
+            .returning(|entry| if entry.url.contains("docs") { 10 } else { 0 });
+        let frontier = Frontier::new(CrawlStrategy::BestFirst(Box::new(scorer)));
+        frontier.push(entry("https://domain.com/blog", 1));
+        frontier.push(entry("https://domain.com/about", 1));
+        frontier.push(entry("https://domain.com/docs", 1));
+
+        assert_eq!(drain(&frontier), vec!["https://domain.com/docs", "https://domain.com/blog", "https://domain.com/about"])
+    }
+
+    #[test]
+    fn entries_should_return_everything_left_in_push_order() {
+        let frontier = Frontier::new(CrawlStrategy::DepthFirst);
+        frontier.push(entry("https://domain.com/a", 1));
+        frontier.push(entry("https://other.com/b", 1));
+        frontier.push(entry("https://domain.com/c", 2));
+        frontier.pop();
+
+        assert_eq!(frontier.entries(), vec![entry("https://domain.com/a", 1), entry("https://other.com/b", 1)])
+    }
+
+    #[test]
+    fn should_keep_the_order_of_the_strategy_when_spilling_to_disk() {
+        let directory = std::env::temp_dir().join(format!("crawly-frontier-spill-{}", std::process::id()));
+        let segments = FrontierSegments::open(directory.to_str().unwrap(), 2, vec![]).unwrap();
+        let frontier = Frontier::new(CrawlStrategy::BreadthFirst).with_segments(segments);
+        frontier.push(entry("https://domain.com/a/b/c", 3));
+        frontier.push(entry("https://domain.com/a/b", 2));
+        frontier.push(entry("https://domain.com/d/e", 2));
+        frontier.push(entry("https://domain.com/a", 1));
+        frontier.push(entry("https://domain.com/d", 1));
+
+        assert!(frontier.entries().len() <= 2);
+        assert!(!frontier.segments().is_empty());
+        assert_eq!(drain(&frontier), vec![
+            "https://domain.com/a",
+            "https://domain.com/d",
+            "https://domain.com/a/b",
+            "https://domain.com/d/e",
+            "https://domain.com/a/b/c"
+        ]);
+        frontier.remove_loaded_segments();
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 0);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn should_keep_entries_in_memory_when_the_segment_cannot_be_written() {
+        let directory = std::env::temp_dir().join(format!("crawly-frontier-unwritable-{}", std::process::id()));
+        let segments = FrontierSegments::open(directory.to_str().unwrap(), 2, vec![]).unwrap();
+        std::fs::remove_dir(&directory).unwrap();
+        std::fs::write(&directory, b"not a directory").unwrap();
+        let frontier = Frontier::new(CrawlStrategy::BreadthFirst).with_segments(segments);
+        frontier.push(entry("https://domain.com/a", 1));
+        frontier.push(entry("https://domain.com/b", 1));
+        frontier.push(entry("https://domain.com/c", 1));
+        frontier.push(entry("https://domain.com/d", 1));
+
+        assert!(frontier.segments().is_empty());
+        assert_eq!(drain(&frontier), vec!["https://domain.com/a", "https://domain.com/b", "https://domain.com/c", "https://domain.com/d"]);
+        std::fs::remove_file(directory).unwrap();
+    }
+
+    #[test]
+    fn should_not_spill_again_right_after_loading_a_segment() {
+        let directory = std::env::temp_dir().join(format!("crawly-frontier-reload-{}", std::process::id()));
+        let segments = FrontierSegments::open(directory.to_str().unwrap(), 4, vec![]).unwrap();
+        let frontier = Frontier::new(CrawlStrategy::BreadthFirst).with_segments(segments);
+        for page in ["a", "b", "c", "d", "e"] {
+            frontier.push(entry(&format!("https://domain.com/{}", page), 1));
+        }
+        frontier.push(entry("https://domain.com/a/f", 2));
+        frontier.push(entry("https://domain.com/a/g", 2));
+        assert_eq!(frontier.segments().len(), 1);
+        frontier.pop();
+        frontier.pop();
+
+        assert_eq!(frontier.pop().map(|entry| entry.url), Some("https://domain.com/c".to_string()));
+        assert!(frontier.segments().is_empty());
+        assert_eq!(drain(&frontier).len(), 4);
+        frontier.remove_loaded_segments();
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn should_take_turns_between_hosts() {
+        let frontier = Frontier::new(CrawlStrategy::BreadthFirst);
+        frontier.push(entry("https://big.com/1", 1));
+        frontier.push(entry("https://big.com/2", 1));
+        frontier.push(entry("https://big.com/3", 1));
+        frontier.push(entry("https://small.com/1", 1));
+        frontier.push(entry("https://other.com/1", 1));
+
+        assert_eq!(drain(&frontier), vec![
+            "https://big.com/1",
+            "https://small.com/1",
+            "https://other.com/1",
+            "https://big.com/2",
+            "https://big.com/3"
+        ])
+    }
+}
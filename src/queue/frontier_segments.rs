@@ -0,0 +1,230 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::queue::error::CheckpointError;
+use crate::queue::frontier::PrioritizedEntry;
+
+const SEGMENT_PREFIX: &str = "segment-";
+const SEGMENT_SUFFIX: &str = ".ndjson";
+
+/// A file of frontier entries moved out of memory, with the rank of its best entry so that
+/// it can be loaded back when its turn comes without reading it.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SegmentInfo {
+    pub file: String,
+    pub entries: usize,
+    pub best_priority: i64,
+    pub best_sequence: u64,
+    pub last_sequence: u64,
+}
+
+/// The entries of a frontier that did not fit in memory, in segment files on disk. Segments
+/// are never changed once written. A segment that was loaded back is only deleted once
+/// nothing refers to it any more, which for a checkpointed crawl is the next snapshot.
+pub struct FrontierSegments {
+    directory: PathBuf,
+    memory_limit: usize,
+    next_id: u64,
+    segments: Vec<SegmentInfo>,
+    retired: Vec<SegmentInfo>,
+}
+
+impl FrontierSegments {
+    /// Keeps up to `memory_limit` entries in memory and the rest in `directory`, picking up the
+    /// segments of a resumed crawl. Any other segment in the directory is left over from a
+    /// crawl that stopped and is deleted.
+    pub fn open(directory: &str, memory_limit: usize, segments: Vec<SegmentInfo>) -> Result<FrontierSegments, CheckpointError> {
+        fs::create_dir_all(directory)?;
+        let directory = PathBuf::from(directory);
+        let mut next_id = 0;
+        for file in fs::read_dir(&directory)? {
+            let path = file?.path();
+            let id = match segment_id(&path) {
+                Some(id) => id,
+                None => continue,
+            };
+            if segments.iter().any(|segment| Path::new(&segment.file) == path) {
+                next_id = next_id.max(id + 1);
+            } else {
+                fs::remove_file(&path)?;
+            }
+        }
+        Ok(FrontierSegments { directory, memory_limit: memory_limit.max(2), next_id, segments, retired: vec![] })
+    }
+
+    pub fn memory_limit(&self) -> usize {
+        self.memory_limit
+    }
+
+    pub fn segments(&self) -> Vec<SegmentInfo> {
+        self.segments.clone()
+    }
+
+    /// The sequence the frontier continues from, so that entries pushed after a restart are
+    /// ordered after the spilled ones.
+    pub fn last_sequence(&self) -> Option<u64> {
+        self.segments.iter().map(|segment| segment.last_sequence).max()
+    }
+
+    /// The rank of the best spilled entry.
+    pub fn best(&self) -> Option<(i64, u64)> {
+        self.segments.iter()
+            .map(|segment| (segment.best_priority, segment.best_sequence))
+            .max_by(|(priority, sequence), (other_priority, other_sequence)|
+                priority.cmp(other_priority).then_with(|| other_sequence.cmp(sequence)))
+    }
+
+    /// Writes the entries to a new segment, handing them back with the error when it cannot
+    /// be written.
+    pub fn spill(&mut self, entries: Vec<PrioritizedEntry>) -> Result<(), (CheckpointError, Vec<PrioritizedEntry>)> {
+        let best = match entries.iter().max() {
+            Some(best) => (best.priority, best.sequence),
+            None => return Ok(()),
+        };
+        let path = self.directory.join(format!("{}{}{}", SEGMENT_PREFIX, self.next_id, SEGMENT_SUFFIX));
+        if let Err(err) = write_segment(&path, &entries) {
+            let _ = fs::remove_file(&path);
+            return Err((err, entries));
+        }
+        self.next_id += 1;
+        self.segments.push(SegmentInfo {
+            file: path.to_string_lossy().to_string(),
+            entries: entries.len(),
+            best_priority: best.0,
+            best_sequence: best.1,
+            last_sequence: entries.iter().map(|entry| entry.sequence).max().unwrap_or(0),
+        });
+        Ok(())
+    }
+
+    /// Reads back the segment holding the best spilled entry.
+    pub fn load_best(&mut self) -> Result<Vec<PrioritizedEntry>, CheckpointError> {
+        let best = match self.best() {
+            Some(best) => best,
+            None => return Ok(vec![]),
+        };
+        let index = self.segments.iter()
+            .position(|segment| (segment.best_priority, segment.best_sequence) == best)
+            .unwrap_or(0);
+        let segment = self.segments.remove(index);
+        let entries = read_segment(Path::new(&segment.file));
+        self.retired.push(segment);
+        entries
+    }
+
+    /// Every link still spilled, read one segment at a time.
+    pub fn links(&self) -> Result<Vec<String>, CheckpointError> {
+        let mut links = vec![];
+        for segment in &self.segments {
+            links.extend(read_segment(Path::new(&segment.file))?.into_iter().map(|entry| entry.entry.url));
+        }
+        Ok(links)
+    }
+
+    /// Deletes the segments that were loaded back.
+    pub fn remove_retired(&mut self) {
+        for segment in self.retired.drain(..) {
+            if let Err(err) = fs::remove_file(&segment.file) {
+                log::warn!("Unable to remove frontier segment {}: {}", segment.file, err);
+            }
+        }
+    }
+}
+
+fn segment_id(path: &Path) -> Option<u64> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix(SEGMENT_PREFIX)?
+        .strip_suffix(SEGMENT_SUFFIX)?
+        .parse()
+        .ok()
+}
+
+fn write_segment(path: &Path, entries: &[PrioritizedEntry]) -> Result<(), CheckpointError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    for entry in entries {
+        serde_json::to_writer(&mut writer, entry)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    Ok(())
+}
+
+fn read_segment(path: &Path) -> Result<Vec<PrioritizedEntry>, CheckpointError> {
+    let mut entries = vec![];
+    for line in BufReader::new(File::open(path)?).lines() {
+        entries.push(serde_json::from_str(&line?)?);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::queue::frontier::{FrontierEntry, PrioritizedEntry};
+    use crate::queue::frontier_segments::FrontierSegments;
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("crawly-segments-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn prioritized(url: &str, priority: i64, sequence: u64) -> PrioritizedEntry {
+        PrioritizedEntry { priority, sequence, entry: FrontierEntry::seed(url) }
+    }
+
+    #[test]
+    fn should_load_the_segment_with_the_best_entry_first() {
+        let directory = directory("best");
+        let mut segments = FrontierSegments::open(directory.to_str().unwrap(), 10, vec![]).unwrap();
+        segments.spill(vec![prioritized("https://domain.com/a", -2, 1), prioritized("https://domain.com/b", -3, 2)]).unwrap();
+        segments.spill(vec![prioritized("https://domain.com/c", -1, 3)]).unwrap();
+
+        let loaded = segments.load_best().unwrap();
+
+        assert_eq!(loaded.into_iter().map(|prioritized| prioritized.entry.url).collect::<Vec<String>>(), vec!["https://domain.com/c"]);
+        assert_eq!(segments.best(), Some((-2, 1)));
+        assert_eq!(segments.links().unwrap(), vec!["https://domain.com/a", "https://domain.com/b"]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn should_keep_loaded_segments_until_they_are_removed() {
+        let directory = directory("retired");
+        let mut segments = FrontierSegments::open(directory.to_str().unwrap(), 10, vec![]).unwrap();
+        segments.spill(vec![prioritized("https://domain.com/a", 0, 1)]).unwrap();
+        let file = segments.segments()[0].file.clone();
+
+        segments.load_best().unwrap();
+        assert!(PathBuf::from(&file).exists());
+        segments.remove_retired();
+
+        assert!(!PathBuf::from(&file).exists());
+        assert!(segments.segments().is_empty());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn should_pick_up_listed_segments_and_delete_the_others_when_reopened() {
+        let directory = directory("reopen");
+        let mut segments = FrontierSegments::open(directory.to_str().unwrap(), 10, vec![]).unwrap();
+        segments.spill(vec![prioritized("https://domain.com/a", 0, 7)]).unwrap();
+        segments.spill(vec![prioritized("https://domain.com/b", 0, 8)]).unwrap();
+        let listed = vec![segments.segments()[0].clone()];
+        let orphan = segments.segments()[1].file.clone();
+
+        let reopened = FrontierSegments::open(directory.to_str().unwrap(), 10, listed.clone()).unwrap();
+
+        assert_eq!(reopened.segments(), listed);
+        assert_eq!(reopened.last_sequence(), Some(7));
+        assert!(!PathBuf::from(orphan).exists());
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+/// An append only list of links stored compactly: the scheme and host of each link is kept
+/// once, and the rest of every link is packed into a single buffer, so a link costs its path
+/// and three indexes rather than a `String` of its own.
+#[derive(Default)]
+pub struct InternedUrls {
+    origins: Vec<String>,
+    origin_ids: HashMap<String, usize>,
+    paths: Vec<u8>,
+    entries: Vec<(usize, usize, usize)>,
+}
+
+impl InternedUrls {
+    pub fn push(&mut self, link: &str) {
+        let (origin, path) = split_origin(link);
+        let origin_id = match self.origin_ids.get(origin) {
+            Some(id) => *id,
+            None => {
+                let id = self.origins.len();
+                self.origins.push(origin.to_string());
+                self.origin_ids.insert(origin.to_string(), id);
+                id
+            }
+        };
+        self.entries.push((origin_id, self.paths.len(), path.len()));
+        self.paths.extend_from_slice(path.as_bytes());
+    }
+
+    pub fn links(&self) -> Vec<String> {
+        self.entries.iter()
+            .map(|(origin, offset, length)| {
+                let path = &self.paths[*offset..*offset + *length];
+                format!("{}{}", self.origins[*origin], String::from_utf8_lossy(path))
+            })
+            .collect()
+    }
+}
+
+fn split_origin(link: &str) -> (&str, &str) {
+    let host_start = link.find("://").map(|index| index + 3).unwrap_or(0);
+    match link[host_start..].find('/') {
+        Some(index) => link.split_at(host_start + index),
+        None => (link, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::queue::interned_urls::InternedUrls;
+
+    #[test]
+    fn should_return_links_in_the_order_they_were_added() {
+        let mut urls = InternedUrls::default();
+        urls.push("https://domain.com/a/b?c=d");
+        urls.push("https://other.com");
+        urls.push("https://domain.com/");
+
+        assert_eq!(urls.links(), vec!["https://domain.com/a/b?c=d", "https://other.com", "https://domain.com/"]);
+        assert_eq!(urls.links().len(), 3)
+    }
+
+    #[test]
+    fn should_store_each_origin_once() {
+        let mut urls = InternedUrls::default();
+        urls.push("https://domain.com/a");
+        urls.push("https://domain.com/b");
+
+        assert_eq!(urls.origins, vec!["https://domain.com"]);
+        assert_eq!(urls.paths, b"/a/b".to_vec())
+    }
+}
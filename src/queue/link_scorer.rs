@@ -0,0 +1,118 @@
+use std::convert::TryFrom;
+use std::str::FromStr;
+use url::Url;
+
+use crate::queue::QueueConfigurationError;
+use crate::queue::frontier::FrontierEntry;
+
+#[cfg_attr(test, mockall::automock)]
+pub trait LinkScorer: Sync + Send {
+    fn score(&self, entry: &FrontierEntry) -> i64;
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScoreRule {
+    pattern: String,
+    weight: i64,
+}
+
+impl TryFrom<&str> for ScoreRule {
+    type Error = QueueConfigurationError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let invalid = || QueueConfigurationError::InvalidScoreRule(value.to_string());
+        let (pattern, weight) = value.rsplit_once('=').ok_or_else(invalid)?;
+        if pattern.is_empty() {
+            return Err(invalid());
+        }
+        Ok(ScoreRule {
+            pattern: pattern.to_lowercase(),
+            weight: i64::from_str(weight.trim()).map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Scores links by summing the weights of the rules matching their path and anchor text,
+/// minus a penalty for every segment in the path.
+pub struct PatternScorer {
+    path_rules: Vec<ScoreRule>,
+    anchor_rules: Vec<ScoreRule>,
+    depth_penalty: i64,
+}
+
+impl PatternScorer {
+    pub fn new(path_rules: Vec<ScoreRule>, anchor_rules: Vec<ScoreRule>, depth_penalty: i64) -> PatternScorer {
+        PatternScorer {
+            path_rules,
+            anchor_rules,
+            depth_penalty,
+        }
+    }
+
+    fn matching_weight(rules: &[ScoreRule], value: &str) -> i64 {
+        rules.iter()
+            .filter(|rule| value.contains(rule.pattern.as_str()))
+            .map(|rule| rule.weight)
+            .sum()
+    }
+}
+
+impl LinkScorer for PatternScorer {
+    fn score(&self, entry: &FrontierEntry) -> i64 {
+        let path = Url::parse(&entry.url)
+            .map(|url| url.path().to_lowercase())
+            .unwrap_or_default();
+        let path_depth = path.split('/').filter(|segment| !segment.is_empty()).count() as i64;
+        PatternScorer::matching_weight(&self.path_rules, &path)
+            + PatternScorer::matching_weight(&self.anchor_rules, &entry.anchor_text.to_lowercase())
+            - self.depth_penalty * path_depth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use crate::queue::QueueConfigurationError;
+    use crate::queue::frontier::FrontierEntry;
+    use crate::queue::link_scorer::{LinkScorer, PatternScorer, ScoreRule};
+
+    fn entry(url: &str, anchor_text: &str) -> FrontierEntry {
+        FrontierEntry { url: url.to_string(), depth: 1, anchor_text: anchor_text.to_string(), referrer: None }
+    }
+
+    #[test]
+    fn should_parse_score_rule_from_pattern_and_weight() {
+        let rule = ScoreRule::try_from("/docs/=10");
+
+        assert_eq!(rule, Ok(ScoreRule { pattern: "/docs/".to_string(), weight: 10 }))
+    }
+
+    #[test]
+    fn should_fail_to_parse_score_rule_without_weight() {
+        let rule = ScoreRule::try_from("/docs/");
+
+        assert_eq!(rule, Err(QueueConfigurationError::InvalidScoreRule("/docs/".to_string())))
+    }
+
+    #[test]
+    fn should_add_weights_of_matching_path_and_anchor_rules() {
+        let scorer = PatternScorer::new(
+            vec![ScoreRule::try_from("/docs/=10").unwrap(), ScoreRule::try_from("/blog/=-5").unwrap()],
+            vec![ScoreRule::try_from("guide=3").unwrap()],
+            0);
+
+        let score = scorer.score(&entry("https://domain.com/docs/intro.html", "Getting Started Guide"));
+
+        assert_eq!(score, 13)
+    }
+
+    #[test]
+    fn should_penalise_deep_paths() {
+        let scorer = PatternScorer::new(vec![], vec![], 2);
+
+        let score = scorer.score(&entry("https://domain.com/a/b/c.html", ""));
+
+        assert_eq!(score, -6)
+    }
+}
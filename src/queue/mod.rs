@@ -1,17 +1,107 @@
-use std::convert::TryFrom;
+use std::sync::Arc;
 
-pub use crawl_queue::CrawlQueue;
+use crate::client::AddressPolicy;
+
+pub use bloom_filter::BloomFilterSettings;
+pub use checkpoint::{Checkpoint, CheckpointStore};
+pub use crawl_queue::{Admission, CrawlQueue, QueueSettings};
+pub use error::QueueConfigurationError;
+pub use frontier::{CrawlStrategy, FrontierEntry};
+pub use frontier_segments::{FrontierSegments, SegmentInfo};
+pub use link_scorer::{PatternScorer, ScoreRule};
+pub use pattern_decider::UrlPattern;
+pub use rejection_log::RejectionLog;
+pub use site_policy::SitePolicy;
+pub use resource_type_decider::{DiscoveredResources, ResourceTypePolicy};
+pub use trap_decider::TrapThresholds;
+use pattern_decider::{exclude_decider, include_decider, SeedDecider};
+use path_prefix_decider::PathPrefixDecider;
+use resource_type_decider::ResourceTypeDecider;
+use trap_decider::TrapDecider;
+use admission_observer::AdmissionObserver;
+use async_decider::{AsyncQueueAdditionDecider, LimitedDecider};
+pub use decider_combinators::{AllOf, AnyOf, Not};
+use content_type_decider::ContentTypeDecider;
+pub use queue_addition_decider::{Decision, QueueAdditionDecider};
 use queue_addition_decider::{AllowOnlySameDomainDecider, IgnoreJavaScriptLinksDecider};
-use crate::link::LinkConstructionError;
+use site_policy::SuffixList;
 
 mod crawl_queue;
+mod admission_observer;
 mod queue_addition_decider;
+mod decider_combinators;
+mod async_decider;
+mod content_type_decider;
 mod already_exists_decider;
+mod bloom_filter;
+mod interned_urls;
+mod seen_set;
+mod frontier;
+mod frontier_segments;
+mod link_scorer;
+mod pattern_decider;
+mod path_prefix_decider;
+mod resource_type_decider;
+mod trap_decider;
+mod rejection_log;
+mod checkpoint;
+mod site_policy;
+mod error;
+
+#[derive(Default)]
+pub struct ScopeSettings {
+    pub include: Vec<UrlPattern>,
+    pub exclude: Vec<UrlPattern>,
+    pub site: SitePolicy,
+    pub restrict_to_seed_path: bool,
+    pub path_prefixes: Vec<String>,
+    pub resource_types: ResourceTypePolicy,
+    pub resources: Arc<DiscoveredResources>,
+    pub traps: TrapThresholds,
+    /// Asks the server for the content type of links that do not look like pages.
+    pub check_content_type: bool,
+    /// How many async checks run at the same time.
+    pub check_concurrency: usize,
+    /// Which addresses the checks may connect to.
+    pub address_policy: AddressPolicy,
+    /// Deciders run after the built in ones.
+    pub deciders: Vec<Box<dyn QueueAdditionDecider>>,
+}
 
-pub fn create_queue(parent: &str) -> Result<CrawlQueue, LinkConstructionError> {
-    let queue = CrawlQueue::new(vec![
+pub fn create_queue(seeds: &[String], scope: ScopeSettings, settings: QueueSettings) -> Result<CrawlQueue, QueueConfigurationError> {
+    let suffixes = if scope.site.include_subdomains {
+        Some(SuffixList::for_policy(&scope.site)?)
+    } else { None };
+    let mut deciders: Vec<Box<dyn QueueAdditionDecider>> = vec![
         Box::new(IgnoreJavaScriptLinksDecider),
-        Box::new(AllowOnlySameDomainDecider::try_from(parent)?)
-    ]);
-    Ok(queue)
-}
\ No newline at end of file
+        Box::new(AllowOnlySameDomainDecider::with_policy(seeds, scope.site, suffixes)?),
+    ];
+    if !scope.include.is_empty() {
+        deciders.push(Box::new(include_decider(scope.include, seeds)));
+    }
+    if !scope.exclude.is_empty() {
+        deciders.push(Box::new(exclude_decider(scope.exclude)));
+    }
+    if scope.restrict_to_seed_path || !scope.path_prefixes.is_empty() {
+        let prefix_seeds = if scope.restrict_to_seed_path { seeds } else { &[] };
+        // Seeds are always allowed, so that a crawl can start outside the prefixes.
+        deciders.push(Box::new(AnyOf::new("path-prefix", vec![
+            Box::new(SeedDecider::new(seeds)),
+            Box::new(PathPrefixDecider::new(prefix_seeds, &scope.path_prefixes)?),
+        ])));
+    }
+    deciders.push(Box::new(ResourceTypeDecider::new(&scope.resource_types)));
+    let traps = TrapDecider::new(scope.traps);
+    let query_variants = traps.query_variants();
+    deciders.push(Box::new(traps));
+    deciders.extend(scope.deciders);
+    let mut async_deciders: Vec<Box<dyn AsyncQueueAdditionDecider>> = vec![];
+    if scope.check_content_type {
+        let decider = ContentTypeDecider::new(crate::client::create_http_client(scope.address_policy));
+        async_deciders.push(Box::new(LimitedDecider::new(Box::new(decider), scope.check_concurrency)));
+    }
+    let observers: Vec<Arc<dyn AdmissionObserver>> = vec![scope.resources, query_variants];
+    Ok(CrawlQueue::with_settings(deciders, settings)
+        .with_async_deciders(async_deciders)
+        .with_observers(observers))
+}
@@ -0,0 +1,166 @@
+use percent_encoding::percent_decode_str;
+use url::Url;
+
+use crate::link::LinkConstructionError;
+use crate::queue::queue_addition_decider::{Decision, QueueAdditionDecider};
+
+struct PathPrefix {
+    host: Option<String>,
+    path: String,
+}
+
+impl std::fmt::Display for PathPrefix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}/", self.host.as_deref().unwrap_or(""), self.path)
+    }
+}
+
+impl PathPrefix {
+    fn new(host: Option<String>, path: &str) -> PathPrefix {
+        PathPrefix {
+            host,
+            path: normalize(path).trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn contains(&self, url: &Url) -> bool {
+        if let Some(host) = &self.host {
+            if url.host_str() != Some(host.as_str()) {
+                return false;
+            }
+        }
+        let path = normalize(url.path());
+        match path.strip_prefix(self.path.as_str()) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+}
+
+fn normalize(path: &str) -> String {
+    percent_decode_str(path).decode_utf8_lossy().to_string()
+}
+
+/// The directory a seed lives in: the seed's path when it ends with a slash or its last
+/// segment has no extension, and the parent of the last segment otherwise.
+fn seed_directory(url: &Url) -> String {
+    let path = url.path();
+    match path.rsplit_once('/') {
+        Some((parent, last)) if last.contains('.') => format!("{}/", parent),
+        _ => path.to_string(),
+    }
+}
+
+/// Allows only links under one of the prefixes. `/docs` allows `/docs`, `/docs/` and
+/// `/docs/intro` but not `/docs-old`.
+pub struct PathPrefixDecider {
+    prefixes: Vec<PathPrefix>,
+}
+
+impl PathPrefixDecider {
+    /// Builds the prefixes from the directory of every seed, on the seed's host, and from
+    /// the given prefixes. Those can be paths, allowed on every host, or full URLs.
+    pub fn new(seeds: &[String], prefixes: &[String]) -> Result<PathPrefixDecider, LinkConstructionError> {
+        let mut path_prefixes = vec![];
+        for seed in seeds {
+            let url = Url::parse(seed)?;
+            path_prefixes.push(PathPrefix::new(url.host_str().map(|host| host.to_string()), &seed_directory(&url)));
+        }
+        for prefix in prefixes {
+            if prefix.starts_with('/') {
+                path_prefixes.push(PathPrefix::new(None, prefix));
+            } else {
+                let url = Url::parse(prefix)?;
+                path_prefixes.push(PathPrefix::new(url.host_str().map(|host| host.to_string()), url.path()));
+            }
+        }
+        Ok(PathPrefixDecider { prefixes: path_prefixes })
+    }
+}
+
+impl QueueAdditionDecider for PathPrefixDecider {
+    fn decide(&self, link: &str) -> Decision {
+        let url = match Url::parse(link) {
+            Ok(url) => url,
+            Err(err) => return Decision::reject("path-prefix", format!("link cannot be parsed: {}", err)),
+        };
+        match self.prefixes.iter().find(|prefix| prefix.contains(&url)) {
+            Some(prefix) => Decision::accept("path-prefix", format!("is under {}", prefix)),
+            None => Decision::reject("path-prefix", "is not under any allowed path prefix"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::queue::path_prefix_decider::PathPrefixDecider;
+    use crate::queue::queue_addition_decider::QueueAdditionDecider;
+
+    fn seeded(seed: &str) -> PathPrefixDecider {
+        PathPrefixDecider::new(&[seed.to_string()], &[]).unwrap()
+    }
+
+    #[test]
+    fn should_allow_only_links_under_the_seed_directory() {
+        let decider = seeded("https://example.com/docs/v2/");
+
+        assert!(decider.decide("https://example.com/docs/v2/").accepted);
+        assert!(decider.decide("https://example.com/docs/v2/intro/setup.html").accepted);
+        assert!(!decider.decide("https://example.com/docs/v1/").accepted);
+        assert!(!decider.decide("https://example.com/").accepted);
+    }
+
+    #[test]
+    fn should_treat_seed_without_trailing_slash_as_directory() {
+        let decider = seeded("https://example.com/docs/v2");
+
+        assert!(decider.decide("https://example.com/docs/v2").accepted);
+        assert!(decider.decide("https://example.com/docs/v2/intro.html").accepted);
+        assert!(!decider.decide("https://example.com/docs/v2beta/intro.html").accepted);
+    }
+
+    #[test]
+    fn should_use_the_parent_directory_of_seed_pages() {
+        let decider = seeded("https://example.com/docs/v2/index.html");
+
+        assert!(decider.decide("https://example.com/docs/v2/other.html").accepted);
+        assert!(!decider.decide("https://example.com/docs/other.html").accepted);
+    }
+
+    #[test]
+    fn should_compare_percent_decoded_paths() {
+        let decider = seeded("https://example.com/docs/my%20guide/");
+
+        assert!(decider.decide("https://example.com/docs/my guide/page.html").accepted);
+        assert!(decider.decide("https://example.com/docs/my%20guide/page.html").accepted);
+        assert!(decider.decide("https://example.com/%64ocs/my%20guide/page.html").accepted);
+    }
+
+    #[test]
+    fn should_resolve_dot_segments_before_comparing() {
+        let decider = seeded("https://example.com/docs/v2/");
+
+        assert!(!decider.decide("https://example.com/docs/v2/../v1/page.html").accepted);
+    }
+
+    #[test]
+    fn should_restrict_seed_prefixes_to_the_seed_host() {
+        let decider = PathPrefixDecider::new(&[
+            "https://example.com/docs/".to_string(),
+            "https://example.org/".to_string()
+        ], &[]).unwrap();
+
+        assert!(decider.decide("https://example.org/blog/").accepted);
+        assert!(!decider.decide("https://example.com/blog/").accepted);
+    }
+
+    #[test]
+    fn should_allow_explicit_path_prefixes_on_any_host() {
+        let decider = PathPrefixDecider::new(&[], &["/api/".to_string(), "https://example.org/guides".to_string()]).unwrap();
+
+        assert!(decider.decide("https://example.com/api/v1").accepted);
+        assert!(decider.decide("https://example.net/api").accepted);
+        assert!(decider.decide("https://example.org/guides/intro").accepted);
+        assert!(!decider.decide("https://example.com/guides/intro").accepted);
+    }
+}
@@ -0,0 +1,206 @@
+use std::convert::TryFrom;
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
+use url::Url;
+
+use crate::queue::QueueConfigurationError;
+use crate::queue::decider_combinators::{AnyOf, Not};
+use crate::queue::queue_addition_decider::{Decision, QueueAdditionDecider};
+
+enum Matcher {
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+/// A `glob:` or `regex:` pattern, glob being the default when there is no prefix.
+/// Patterns starting with `/` are matched against the path of the link, all others
+/// against the full link.
+pub struct UrlPattern {
+    source: String,
+    matcher: Matcher,
+    matches_path: bool,
+}
+
+impl TryFrom<&str> for UrlPattern {
+    type Error = QueueConfigurationError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let invalid = |reason: String| QueueConfigurationError::InvalidPattern(value.to_string(), reason);
+        let (is_regex, pattern) = if let Some(pattern) = value.strip_prefix("regex:") {
+            (true, pattern)
+        } else {
+            (false, value.strip_prefix("glob:").unwrap_or(value))
+        };
+        let matcher = if is_regex {
+            Matcher::Regex(Regex::new(pattern).map_err(|err| invalid(err.to_string()))?)
+        } else {
+            let glob = GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .map_err(|err| invalid(err.kind().to_string()))?;
+            Matcher::Glob(glob.compile_matcher())
+        };
+        let matches_path = pattern.trim_start_matches('^').starts_with('/');
+        Ok(UrlPattern { source: value.to_string(), matcher, matches_path })
+    }
+}
+
+impl UrlPattern {
+    pub fn matches(&self, link: &str) -> bool {
+        let target = if self.matches_path {
+            match Url::parse(link) {
+                Ok(url) => url.path().to_string(),
+                Err(_) => return false,
+            }
+        } else {
+            link.to_string()
+        };
+        match &self.matcher {
+            Matcher::Glob(glob) => glob.is_match(&target),
+            Matcher::Regex(regex) => regex.is_match(&target),
+        }
+    }
+}
+
+impl QueueAdditionDecider for UrlPattern {
+    fn decide(&self, link: &str) -> Decision {
+        if self.matches(link) {
+            Decision::accept("pattern", format!("matches {}", self.source))
+        } else {
+            Decision::reject("pattern", format!("does not match {}", self.source))
+        }
+    }
+}
+
+/// Accepts the seeds of the crawl.
+pub struct SeedDecider {
+    seeds: Vec<String>,
+}
+
+impl SeedDecider {
+    pub fn new(seeds: &[String]) -> SeedDecider {
+        SeedDecider { seeds: seeds.to_vec() }
+    }
+}
+
+impl QueueAdditionDecider for SeedDecider {
+    fn decide(&self, link: &str) -> Decision {
+        if self.seeds.iter().any(|seed| seed == link) {
+            Decision::accept("seed", "is a seed")
+        } else {
+            Decision::reject("seed", "is not a seed")
+        }
+    }
+}
+
+/// Allows only links matching at least one of the patterns. Seeds are always allowed
+/// so that a crawl can start from a page outside the included sections.
+pub fn include_decider(patterns: Vec<UrlPattern>, seeds: &[String]) -> AnyOf {
+    let mut deciders: Vec<Box<dyn QueueAdditionDecider>> = vec![Box::new(SeedDecider::new(seeds))];
+    deciders.extend(patterns.into_iter().map(|pattern| Box::new(pattern) as Box<dyn QueueAdditionDecider>));
+    AnyOf::new("include", deciders)
+}
+
+/// Rejects links matching any of the patterns. Exclusions take precedence over inclusions.
+pub fn exclude_decider(patterns: Vec<UrlPattern>) -> Not {
+    let deciders = patterns.into_iter()
+        .map(|pattern| Box::new(pattern) as Box<dyn QueueAdditionDecider>)
+        .collect();
+    Not::new("exclude", Box::new(AnyOf::new("exclude", deciders)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use crate::queue::QueueConfigurationError;
+    use crate::queue::pattern_decider::{exclude_decider, include_decider, UrlPattern};
+    use crate::queue::queue_addition_decider::{Decision, QueueAdditionDecider};
+
+    fn patterns(values: Vec<&str>) -> Vec<UrlPattern> {
+        values.into_iter().map(|value| UrlPattern::try_from(value).unwrap()).collect()
+    }
+
+    #[test]
+    fn should_match_path_globs_against_the_path_only() {
+        let pattern = UrlPattern::try_from("/docs/**").unwrap();
+
+        assert!(pattern.matches("https://domain.com/docs/v2/index.html"));
+        assert!(!pattern.matches("https://domain.com/blog/docs/index.html"));
+    }
+
+    #[test]
+    fn should_not_cross_path_segments_with_single_star_glob() {
+        let pattern = UrlPattern::try_from("glob:/docs/*.html").unwrap();
+
+        assert!(pattern.matches("https://domain.com/docs/index.html"));
+        assert!(!pattern.matches("https://domain.com/docs/v2/index.html"));
+    }
+
+    #[test]
+    fn should_match_url_globs_against_the_full_url() {
+        let pattern = UrlPattern::try_from("https://*.domain.com/**").unwrap();
+
+        assert!(pattern.matches("https://docs.domain.com/index.html"));
+        assert!(!pattern.matches("http://docs.domain.com/index.html"));
+    }
+
+    #[test]
+    fn should_search_regex_patterns_anywhere_in_the_url() {
+        let pattern = UrlPattern::try_from(r"regex:\?page=\d{3,}").unwrap();
+
+        assert!(pattern.matches("https://domain.com/list?page=999"));
+        assert!(!pattern.matches("https://domain.com/list?page=9"));
+    }
+
+    #[test]
+    fn should_match_anchored_regex_patterns_against_the_path() {
+        let pattern = UrlPattern::try_from(r"regex:^/blog/\d+$").unwrap();
+
+        assert!(pattern.matches("https://domain.com/blog/42"));
+        assert!(!pattern.matches("https://domain.com/en/blog/42"));
+    }
+
+    #[test]
+    fn should_fail_on_invalid_regex() {
+        let pattern = UrlPattern::try_from("regex:(");
+
+        assert!(matches!(pattern, Err(QueueConfigurationError::InvalidPattern(source, _)) if source == "regex:("))
+    }
+
+    #[test]
+    fn should_allow_only_included_links_and_seeds() {
+        let decider = include_decider(patterns(vec!["/docs/**"]), &["https://domain.com/".to_string()]);
+
+        assert!(decider.decide("https://domain.com/docs/intro").accepted);
+        assert!(decider.decide("https://domain.com/").accepted);
+        assert!(!decider.decide("https://domain.com/blog/1").accepted);
+    }
+
+    #[test]
+    fn should_reject_excluded_links() {
+        let decider = exclude_decider(patterns(vec!["/docs/old/**", "/**/*.pdf"]));
+
+        assert!(decider.decide("https://domain.com/docs/intro").accepted);
+        assert!(!decider.decide("https://domain.com/docs/old/intro").accepted);
+        assert!(!decider.decide("https://domain.com/docs/manual.pdf").accepted);
+    }
+
+    #[test]
+    fn should_explain_which_pattern_excluded_a_link() {
+        let decider = exclude_decider(patterns(vec!["/docs/old/**", "/**/*.pdf"]));
+
+        let decision = decider.decide("https://domain.com/docs/manual.pdf");
+
+        assert_eq!(decision, Decision::reject("exclude", "matches /**/*.pdf"))
+    }
+
+    #[test]
+    fn should_explain_why_a_link_was_not_included() {
+        let decider = include_decider(patterns(vec!["/docs/**"]), &[]);
+
+        let decision = decider.decide("https://domain.com/blog/1");
+
+        assert_eq!(decision, Decision::reject("include", "is not a seed; does not match /docs/**"))
+    }
+}
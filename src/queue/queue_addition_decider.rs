@@ -1,88 +1,166 @@
 use std::str::FromStr;
 use std::convert::TryFrom;
-use url::{Url};
+use url::{Host, Url};
 use crate::link::LinkConstructionError;
+use crate::queue::site_policy::{SitePolicy, SuffixList};
+
+/// The outcome of a decider for a link, with the name of the decider that made it and why.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Decision {
+    pub accepted: bool,
+    pub decider: String,
+    pub reason: String,
+}
+
+impl Decision {
+    pub fn accept(decider: &str, reason: impl Into<String>) -> Decision {
+        Decision { accepted: true, decider: decider.to_string(), reason: reason.into() }
+    }
+
+    pub fn reject(decider: &str, reason: impl Into<String>) -> Decision {
+        Decision { accepted: false, decider: decider.to_string(), reason: reason.into() }
+    }
+}
 
 #[cfg_attr(test, mockall::automock)]
 pub trait QueueAdditionDecider: Sync + Send{
-    fn can_add_to_queue(&self, link: &str) -> bool;
+    fn decide(&self, link: &str) -> Decision;
+}
+
+struct Site {
+    scheme: String,
+    host: String,
+    port: Option<u16>,
 }
 
 pub struct AllowOnlySameDomainDecider {
-    parent_domain: String
+    sites: Vec<Site>,
+    policy: SitePolicy,
+    suffixes: Option<SuffixList>,
+}
+
+impl AllowOnlySameDomainDecider {
+    pub fn from_seeds(seeds: &[String]) -> Result<Self, LinkConstructionError> {
+        AllowOnlySameDomainDecider::with_policy(seeds, SitePolicy::default(), None)
+    }
+
+    pub fn with_policy(seeds: &[String], policy: SitePolicy, suffixes: Option<SuffixList>) -> Result<Self, LinkConstructionError> {
+        let mut decider = AllowOnlySameDomainDecider { sites: vec![], policy, suffixes };
+        decider.sites = seeds.iter()
+            .map(|seed| Url::parse(seed).map(|url| decider.site_of(&url)))
+            .collect::<Result<Vec<Site>, _>>()?;
+        Ok(decider)
+    }
+
+    fn site_of(&self, url: &Url) -> Site {
+        let host = match url.host() {
+            Some(Host::Domain(domain)) => self.normalize_domain(domain),
+            Some(host) => host.to_string(),
+            None => "".to_string(),
+        };
+        Site {
+            scheme: url.scheme().to_string(),
+            host,
+            port: url.port_or_known_default(),
+        }
+    }
+
+    fn normalize_domain(&self, domain: &str) -> String {
+        match &self.suffixes {
+            Some(suffixes) if self.policy.include_subdomains => suffixes.registrable_domain(domain),
+            _ if self.policy.www_equivalent => domain.strip_prefix("www.").unwrap_or(domain).to_string(),
+            _ => domain.to_string(),
+        }
+    }
+
+    fn is_same_site(&self, seed: &Site, site: &Site) -> bool {
+        seed.host == site.host
+            && (!self.policy.match_scheme || seed.scheme == site.scheme)
+            && (!self.policy.match_port || seed.port == site.port)
+    }
 }
 
 impl TryFrom<&str> for AllowOnlySameDomainDecider {
     type Error = LinkConstructionError;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        Url::parse(value)
-            .map(|url| AllowOnlySameDomainDecider { parent_domain: url.domain().unwrap_or("").to_string() })
-            .map_err(|err| err.into())
+        AllowOnlySameDomainDecider::from_seeds(&[value.to_string()])
     }
 }
 
 impl QueueAdditionDecider for AllowOnlySameDomainDecider {
-    fn can_add_to_queue(&self, link: &str) -> bool {
-        Url::from_str(link)
-            .map(|uri| uri.host_str().unwrap_or("") == self.parent_domain)
-            .unwrap_or_else(|_| false)
+    fn decide(&self, link: &str) -> Decision {
+        let uri = match Url::from_str(link) {
+            Ok(uri) => uri,
+            Err(err) => return Decision::reject("same-site", format!("link cannot be parsed: {}", err)),
+        };
+        let site = self.site_of(&uri);
+        if self.sites.iter().any(|seed| self.is_same_site(seed, &site)) {
+            Decision::accept("same-site", format!("{} is on the same site as a seed", site.host))
+        } else {
+            Decision::reject("same-site", format!("{} is not on the same site as any seed", uri.host_str().unwrap_or("")))
+        }
     }
 }
 
 pub struct IgnoreJavaScriptLinksDecider;
 
 impl QueueAdditionDecider for IgnoreJavaScriptLinksDecider {
-    fn can_add_to_queue(&self, link: &str) -> bool {
-        !link.starts_with("javascript:")
+    fn decide(&self, link: &str) -> Decision {
+        if link.starts_with("javascript:") {
+            Decision::reject("javascript", "javascript: links are not crawled")
+        } else {
+            Decision::accept("javascript", "not a javascript: link")
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::queue::queue_addition_decider::{MockQueueAdditionDecider, AllowOnlySameDomainDecider, QueueAdditionDecider, IgnoreJavaScriptLinksDecider};
+    use crate::queue::queue_addition_decider::{MockQueueAdditionDecider, AllowOnlySameDomainDecider, QueueAdditionDecider, IgnoreJavaScriptLinksDecider, Decision};
     use crate::queue::CrawlQueue;
+    use crate::queue::site_policy::{SitePolicy, SuffixList};
     use mockall::predicate::eq;
     use std::convert::TryFrom;
 
-    #[test]
-    fn should_not_add_item_when_one_of_the_deciders_fails() {
+    #[tokio::test]
+    async fn should_not_add_item_when_one_of_the_deciders_fails() {
         let mut decider1 = MockQueueAdditionDecider::new();
-        decider1.expect_can_add_to_queue()
+        decider1.expect_decide()
             .with(eq("http://domain.com"))
-            .returning(|_| false);
-        decider1.expect_can_add_to_queue()
+            .returning(|_| Decision::reject("mock", "rejected"));
+        decider1.expect_decide()
             .with(eq("http://test.com"))
-            .returning(|_| true);
+            .returning(|_| Decision::accept("mock", "accepted"));
         let queue = CrawlQueue::new(vec![Box::new(decider1)]);
 
         let added = queue.add_all(vec![
             "http://test.com".to_string(),
             "http://domain.com".to_string()
-        ]);
+        ]).await;
 
         assert_eq!(added, vec!["http://test.com"])
     }
 
-    #[test]
-    fn should_process_all_deciders_to_decide_on_addition() {
+    #[tokio::test]
+    async fn should_process_all_deciders_to_decide_on_addition() {
         let mut decider1 = MockQueueAdditionDecider::new();
-        decider1.expect_can_add_to_queue()
+        decider1.expect_decide()
             .times(2)
-            .return_const(true);
+            .return_const(Decision::accept("mock", "accepted"));
         let mut decider2 = MockQueueAdditionDecider::new();
-        decider2.expect_can_add_to_queue()
+        decider2.expect_decide()
             .times(2)
-            .return_const(true);
+            .return_const(Decision::accept("mock", "accepted"));
         let mut decider3 = MockQueueAdditionDecider::new();
-        decider3.expect_can_add_to_queue()
+        decider3.expect_decide()
             .times(1)
             .with(eq("http://test.com"))
-            .returning(|_| true);
-        decider3.expect_can_add_to_queue()
+            .returning(|_| Decision::accept("mock", "accepted"));
+        decider3.expect_decide()
             .times(1)
             .with(eq("http://domain.com"))
-            .returning(|_| false);
+            .returning(|_| Decision::reject("mock", "rejected"));
         let queue = CrawlQueue::new(vec![
             Box::new(decider1),
             Box::new(decider2),
@@ -92,7 +170,7 @@ mod tests {
         let added = queue.add_all(vec![
             "http://test.com".to_string(),
             "http://domain.com".to_string()
-        ]);
+        ]).await;
 
         assert_eq!(added, vec!["http://test.com"])
     }
@@ -101,7 +179,7 @@ mod tests {
     fn should_allow_only_links_from_same_domain_in_allow_only_same_domain_decider() {
         let decider = AllowOnlySameDomainDecider::try_from("http://www.domain.com/page1.html").unwrap();
 
-        let decision = decider.can_add_to_queue("http://www.domain.com/page2.html");
+        let decision = decider.decide("http://www.domain.com/page2.html").accepted;
 
         assert!(decision)
     }
@@ -110,16 +188,25 @@ mod tests {
     fn should_not_allow_only_links_from_different_domain_in_allow_only_same_domain_decider() {
         let decider = AllowOnlySameDomainDecider::try_from("http://www.domain.com/page1.html").unwrap();
 
-        let decision = decider.can_add_to_queue("http://sub.domain.com/page2.html");
+        let decision = decider.decide("http://sub.domain.com/page2.html").accepted;
 
         assert!(!decision)
     }
 
+    #[test]
+    fn should_explain_rejection_in_allow_only_same_domain_decider() {
+        let decider = AllowOnlySameDomainDecider::try_from("http://www.domain.com/page1.html").unwrap();
+
+        let decision = decider.decide("http://sub.domain.com/page2.html");
+
+        assert_eq!(decision, Decision::reject("same-site", "sub.domain.com is not on the same site as any seed"))
+    }
+
     #[test]
     fn should_not_allow_javascript_links_in_ignore_javascript_links_decider() {
         let decider = IgnoreJavaScriptLinksDecider;
 
-        let decision = decider.can_add_to_queue("javascript:void()");
+        let decision = decider.decide("javascript:void()").accepted;
 
         assert!(!decision)
     }
@@ -128,8 +215,76 @@ mod tests {
     fn should_allow_non_javascript_links_in_ignore_javascript_links_decider() {
         let decider = IgnoreJavaScriptLinksDecider;
 
-        let decision = decider.can_add_to_queue("http://sub.domain.com/page2.html");
+        let decision = decider.decide("http://sub.domain.com/page2.html").accepted;
 
         assert!(decision)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn should_allow_links_from_any_seed_domain_in_allow_only_same_domain_decider() {
+        let decider = AllowOnlySameDomainDecider::from_seeds(&[
+            "http://www.domain.com/".to_string(),
+            "https://other.org/start.html".to_string()
+        ]).unwrap();
+
+        assert!(decider.decide("http://www.domain.com/page2.html").accepted);
+        assert!(decider.decide("https://other.org/page2.html").accepted);
+        assert!(!decider.decide("https://third.net/page2.html").accepted);
+    }
+
+    fn decider(seed: &str, policy: SitePolicy) -> AllowOnlySameDomainDecider {
+        let suffixes = SuffixList::from_bytes("test", b"// ===BEGIN ICANN DOMAINS===\ncom\norg\n// ===END ICANN DOMAINS===\n").unwrap();
+        AllowOnlySameDomainDecider::with_policy(&[seed.to_string()], policy, Some(suffixes)).unwrap()
+    }
+
+    #[test]
+    fn should_allow_links_on_ip_addresses_in_allow_only_same_domain_decider() {
+        let decider = AllowOnlySameDomainDecider::try_from("http://127.0.0.1:8080/").unwrap();
+
+        assert!(decider.decide("http://127.0.0.1:8080/page2.html").accepted);
+        assert!(!decider.decide("http://127.0.0.2:8080/page2.html").accepted);
+    }
+
+    #[test]
+    fn should_allow_subdomains_when_policy_includes_subdomains() {
+        let decider = decider("https://example.com/", SitePolicy { include_subdomains: true, ..SitePolicy::default() });
+
+        assert!(decider.decide("https://docs.example.com/page.html").accepted);
+        assert!(decider.decide("https://www.example.com/page.html").accepted);
+        assert!(!decider.decide("https://example.org/page.html").accepted);
+    }
+
+    #[test]
+    fn should_treat_www_and_apex_as_same_site_when_policy_says_so() {
+        let decider = decider("https://www.example.com/", SitePolicy { www_equivalent: true, ..SitePolicy::default() });
+
+        assert!(decider.decide("https://example.com/page.html").accepted);
+        assert!(decider.decide("https://www.example.com/page.html").accepted);
+        assert!(!decider.decide("https://docs.example.com/page.html").accepted);
+    }
+
+    #[test]
+    fn should_ignore_scheme_and_port_by_default() {
+        let decider = decider("https://example.com/", SitePolicy::default());
+
+        assert!(decider.decide("http://example.com/page.html").accepted);
+        assert!(decider.decide("http://example.com:8080/page.html").accepted);
+    }
+
+    #[test]
+    fn should_reject_other_schemes_and_ports_when_policy_matches_them() {
+        let decider = decider("https://example.com/", SitePolicy { match_scheme: true, match_port: true, ..SitePolicy::default() });
+
+        assert!(decider.decide("https://example.com:443/page.html").accepted);
+        assert!(!decider.decide("http://example.com/page.html").accepted);
+        assert!(!decider.decide("https://example.com:8443/page.html").accepted);
+    }
+
+    #[test]
+    fn should_compare_the_default_port_of_the_scheme_when_policy_matches_ports() {
+        let decider = decider("https://example.com/", SitePolicy { match_port: true, ..SitePolicy::default() });
+
+        assert!(decider.decide("http://example.com:443/page.html").accepted);
+        assert!(!decider.decide("http://example.com/page.html").accepted);
+    }
+}
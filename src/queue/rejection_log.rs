@@ -0,0 +1,69 @@
+use std::io::Write;
+use std::sync::Mutex;
+
+use flurry::HashSet;
+
+use crate::queue::already_exists_decider::ALREADY_SEEN;
+use crate::queue::queue_addition_decider::Decision;
+
+/// Writes one tab separated line per skipped link, naming the decider that rejected it and
+/// why. Every link is written once, and links skipped only because they were seen before
+/// are left out.
+pub struct RejectionLog {
+    logged: HashSet<String>,
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl RejectionLog {
+    pub fn new(writer: Box<dyn Write + Send>) -> RejectionLog {
+        RejectionLog {
+            logged: HashSet::new(),
+            writer: Mutex::new(writer),
+        }
+    }
+
+    pub fn record(&self, link: &str, decision: &Decision) {
+        if decision.decider == ALREADY_SEEN || !self.logged.insert(link.to_string(), &self.logged.guard()) {
+            return;
+        }
+        let mut writer = self.writer.lock().unwrap();
+        if let Err(err) = writeln!(writer, "{}\t{}\t{}", link, decision.decider, decision.reason).and_then(|_| writer.flush()) {
+            log::warn!("Unable to write to the audit log: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    use crate::queue::queue_addition_decider::Decision;
+    use crate::queue::rejection_log::RejectionLog;
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn should_write_each_rejected_link_once() {
+        let buffer = SharedBuffer::default();
+        let log = RejectionLog::new(Box::new(buffer.clone()));
+
+        log.record("https://example.com/a.pdf", &Decision::reject("resource-type", ".pdf is a resource, not a page"));
+        log.record("https://example.com/a.pdf", &Decision::reject("resource-type", ".pdf is a resource, not a page"));
+        log.record("https://example.com/", &Decision::reject("already-seen", "already crawled"));
+
+        let written = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(written, "https://example.com/a.pdf\tresource-type\t.pdf is a resource, not a page\n")
+    }
+}
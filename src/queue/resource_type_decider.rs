@@ -0,0 +1,186 @@
+use flurry::HashSet;
+use serde::Deserialize;
+use url::Url;
+
+use crate::queue::admission_observer::AdmissionObserver;
+use crate::queue::content_type_decider::CONTENT_TYPE;
+use crate::queue::queue_addition_decider::{Decision, QueueAdditionDecider};
+
+pub const RESOURCE_TYPE: &str = "resource-type";
+
+pub const DEFAULT_DENIED_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "gif", "bmp", "webp", "svg", "ico", "tif", "tiff",
+    "mp3", "m4a", "wav", "ogg", "flac", "mp4", "m4v", "webm", "avi", "mov", "mkv", "wmv", "flv",
+    "zip", "gz", "tgz", "bz2", "xz", "7z", "rar", "tar",
+    "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "ods", "odp",
+    "exe", "msi", "dmg", "iso", "apk", "deb", "rpm", "bin",
+    "css", "js", "woff", "woff2", "ttf", "otf", "eot",
+];
+
+/// Extensions and MIME types of links that are not crawled as pages. Entries containing a
+/// `/` are MIME types, where `image/*` covers every image, all others are extensions.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResourceTypePolicy {
+    /// Crawled even when they are denied by default.
+    pub allow: Vec<String>,
+    /// Recorded as resources on top of the default deny list.
+    pub deny: Vec<String>,
+}
+
+/// Links to images, archives, documents and other non-page resources found during the crawl,
+/// as the links the queue turns down for their resource or content type.
+#[derive(Default)]
+pub struct DiscoveredResources {
+    links: HashSet<String>,
+}
+
+impl DiscoveredResources {
+    pub fn record(&self, link: &str) {
+        self.links.insert(link.to_string(), &self.links.guard());
+    }
+
+    pub fn links(&self) -> Vec<String> {
+        let guard = self.links.guard();
+        self.links.iter(&guard).cloned().collect()
+    }
+}
+
+impl AdmissionObserver for DiscoveredResources {
+    fn rejected(&self, link: &str, decision: &Decision) {
+        if decision.decider == RESOURCE_TYPE || decision.decider == CONTENT_TYPE {
+            self.record(link);
+        }
+    }
+}
+
+enum ResourceType {
+    Extension(String),
+    Mime(String, String),
+}
+
+impl ResourceType {
+    fn parse(value: &str) -> ResourceType {
+        let value = value.trim().to_lowercase();
+        match value.split_once('/') {
+            Some((type_, subtype)) => ResourceType::Mime(type_.to_string(), subtype.to_string()),
+            None => ResourceType::Extension(value.trim_start_matches('.').to_string()),
+        }
+    }
+
+    fn matches(&self, extension: &str) -> bool {
+        match self {
+            ResourceType::Extension(expected) => expected == extension,
+            ResourceType::Mime(type_, subtype) => mime_guess::from_ext(extension)
+                .iter()
+                .any(|mime| mime.type_() == type_.as_str() && (subtype == "*" || mime.subtype() == subtype.as_str())),
+        }
+    }
+}
+
+/// Keeps links to resources out of the queue, going by the extension of the link and the
+/// MIME type guessed from it.
+pub struct ResourceTypeDecider {
+    allowed: Vec<ResourceType>,
+    denied: Vec<ResourceType>,
+}
+
+impl ResourceTypeDecider {
+    pub fn new(policy: &ResourceTypePolicy) -> ResourceTypeDecider {
+        ResourceTypeDecider {
+            allowed: policy.allow.iter().map(|value| ResourceType::parse(value)).collect(),
+            denied: DEFAULT_DENIED_EXTENSIONS.iter()
+                .map(|extension| extension.to_string())
+                .chain(policy.deny.iter().cloned())
+                .map(|value| ResourceType::parse(&value))
+                .collect(),
+        }
+    }
+
+    fn extension(link: &str) -> Option<String> {
+        let url = Url::parse(link).ok()?;
+        let last_segment = url.path_segments()?.next_back()?.to_string();
+        last_segment.rsplit_once('.').map(|(_, extension)| extension.to_lowercase())
+    }
+}
+
+impl QueueAdditionDecider for ResourceTypeDecider {
+    fn decide(&self, link: &str) -> Decision {
+        let extension = match ResourceTypeDecider::extension(link) {
+            Some(extension) => extension,
+            None => return Decision::accept(RESOURCE_TYPE, "has no extension"),
+        };
+        if self.allowed.iter().any(|allowed| allowed.matches(&extension)) {
+            return Decision::accept(RESOURCE_TYPE, format!(".{} is allowed", extension));
+        }
+        if self.denied.iter().any(|denied| denied.matches(&extension)) {
+            return Decision::reject(RESOURCE_TYPE, format!(".{} is a resource, not a page", extension));
+        }
+        Decision::accept(RESOURCE_TYPE, format!(".{} is not denied", extension))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::queue::admission_observer::AdmissionObserver;
+    use crate::queue::queue_addition_decider::{Decision, QueueAdditionDecider};
+    use crate::queue::resource_type_decider::{DiscoveredResources, ResourceTypeDecider, ResourceTypePolicy};
+
+    fn decider(allow: Vec<&str>, deny: Vec<&str>) -> ResourceTypeDecider {
+        let policy = ResourceTypePolicy {
+            allow: allow.iter().map(|value| value.to_string()).collect(),
+            deny: deny.iter().map(|value| value.to_string()).collect(),
+        };
+        ResourceTypeDecider::new(&policy)
+    }
+
+    #[test]
+    fn should_allow_pages() {
+        let decider = decider(vec![], vec![]);
+
+        assert!(decider.decide("https://example.com/").accepted);
+        assert!(decider.decide("https://example.com/docs").accepted);
+        assert!(decider.decide("https://example.com/docs/index.html").accepted);
+        assert!(decider.decide("https://example.com/search.php?q=file.zip").accepted);
+    }
+
+    #[test]
+    fn should_reject_binary_resources_by_default() {
+        let decider = decider(vec![], vec![]);
+
+        assert!(!decider.decide("https://example.com/images/logo.JPG").accepted);
+        assert!(!decider.decide("https://example.com/files/manual.pdf").accepted);
+        assert!(decider.decide("https://example.com/docs/index.html").accepted);
+    }
+
+    #[test]
+    fn should_allow_extensions_and_mime_types_in_the_allow_list() {
+        let decider = decider(vec!["pdf", "image/*"], vec![]);
+
+        assert!(decider.decide("https://example.com/files/manual.pdf").accepted);
+        assert!(decider.decide("https://example.com/images/logo.png").accepted);
+        assert!(!decider.decide("https://example.com/files/archive.zip").accepted);
+    }
+
+    #[test]
+    fn should_deny_extra_extensions_and_mime_types() {
+        let decider = decider(vec![], vec!["json", "text/xml"]);
+
+        assert!(!decider.decide("https://example.com/api/data.json").accepted);
+        assert!(!decider.decide("https://example.com/feed.xml").accepted);
+    }
+
+    #[test]
+    fn should_record_only_links_rejected_for_their_type_as_resources() {
+        let resources = DiscoveredResources::default();
+
+        resources.rejected("https://example.com/logo.png", &Decision::reject("resource-type", ".png is a resource, not a page"));
+        resources.rejected("https://example.com/download", &Decision::reject("content-type", "server reports application/zip, not a page"));
+        resources.rejected("https://elsewhere.com/logo.png", &Decision::reject("same-domain", "different site"));
+        resources.queued("https://example.com/");
+
+        let mut recorded = resources.links();
+        recorded.sort();
+        assert_eq!(recorded, vec!["https://example.com/download", "https://example.com/logo.png"])
+    }
+}
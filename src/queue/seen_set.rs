@@ -0,0 +1,102 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use flurry::HashSet;
+
+use crate::queue::bloom_filter::{BloomFilter, BloomFilterSettings};
+use crate::queue::interned_urls::InternedUrls;
+
+/// The pages a crawl has visited. By default every link is kept in a set and looked up
+/// exactly. With a Bloom filter, lookups go to the filter, whose memory does not grow with
+/// the crawl, and only a count of the pages is kept, unless the links are needed for the
+/// snapshots of a checkpoint, where they are kept compactly. A page the filter wrongly
+/// takes for visited is not crawled.
+pub enum SeenSet {
+    Exact(HashSet<String>),
+    Probabilistic(BloomFilter, AtomicUsize, Option<Mutex<InternedUrls>>),
+}
+
+impl SeenSet {
+    pub fn new(bloom_filter: Option<BloomFilterSettings>, keep_links: bool) -> SeenSet {
+        match bloom_filter {
+            Some(settings) => SeenSet::Probabilistic(
+                BloomFilter::new(settings),
+                AtomicUsize::new(0),
+                if keep_links { Some(Mutex::new(InternedUrls::default())) } else { None }),
+            None => SeenSet::Exact(HashSet::new()),
+        }
+    }
+
+    pub fn contains(&self, link: &str) -> bool {
+        match self {
+            SeenSet::Exact(links) => links.contains(link, &links.guard()),
+            SeenSet::Probabilistic(filter, _, _) => filter.contains(link),
+        }
+    }
+
+    /// Records a visited page. Each page is visited once, so pages are not checked against
+    /// the filter before they are counted, and a false positive never drops one from `links`.
+    pub fn insert(&self, link: &str) {
+        match self {
+            SeenSet::Exact(links) => { links.insert(link.to_string(), &links.guard()); }
+            SeenSet::Probabilistic(filter, count, links) => {
+                filter.insert(link);
+                count.fetch_add(1, Ordering::SeqCst);
+                if let Some(links) = links {
+                    links.lock().unwrap().push(link);
+                }
+            }
+        }
+    }
+
+    /// The visited pages, none when a Bloom filter does not keep them.
+    pub fn links(&self) -> Vec<String> {
+        match self {
+            SeenSet::Exact(links) => links.iter(&links.guard()).cloned().collect(),
+            SeenSet::Probabilistic(_, _, Some(links)) => links.lock().unwrap().links(),
+            SeenSet::Probabilistic(_, _, None) => vec![],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            SeenSet::Exact(links) => links.len(),
+            SeenSet::Probabilistic(_, count, _) => count.load(Ordering::SeqCst),
+        }
+    }
+}
+
+impl Default for SeenSet {
+    fn default() -> Self {
+        SeenSet::new(None, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::queue::bloom_filter::BloomFilterSettings;
+    use crate::queue::seen_set::SeenSet;
+
+    #[test]
+    fn should_contain_inserted_links_with_either_kind_of_set() {
+        for set in [SeenSet::new(None, false), SeenSet::new(Some(BloomFilterSettings::new(100, 0.01).unwrap()), true)] {
+            set.insert("https://domain.com/a");
+
+            assert!(set.contains("https://domain.com/a"));
+            assert!(!set.contains("https://domain.com/b"));
+            assert_eq!(set.links(), vec!["https://domain.com/a"]);
+            assert_eq!(set.len(), 1)
+        }
+    }
+
+    #[test]
+    fn should_only_count_pages_when_a_bloom_filter_does_not_keep_them() {
+        let set = SeenSet::new(Some(BloomFilterSettings::new(100, 0.01).unwrap()), false);
+        set.insert("https://domain.com/a");
+        set.insert("https://domain.com/b");
+
+        assert!(set.contains("https://domain.com/a"));
+        assert_eq!(set.links(), Vec::<String>::new());
+        assert_eq!(set.len(), 2)
+    }
+}
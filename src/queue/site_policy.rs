@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::Path;
+use std::str::from_utf8;
+
+use publicsuffix::{List, Psl};
+use serde::Deserialize;
+
+use crate::queue::QueueConfigurationError;
+
+pub const DEFAULT_PUBLIC_SUFFIX_LIST: &str = "/usr/share/publicsuffix/public_suffix_list.dat";
+
+/// Decides which links count as being on the same site as one of the seeds.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SitePolicy {
+    /// Treats every host under the seed's registrable domain (`docs.example.com` for
+    /// `example.com`) as the same site.
+    pub include_subdomains: bool,
+    /// Treats `www.example.com` and `example.com` as the same site.
+    pub www_equivalent: bool,
+    /// Treats `http` and `https` links as different sites.
+    pub match_scheme: bool,
+    /// Treats links on a different port as different sites.
+    pub match_port: bool,
+    /// The public suffix list used to find registrable domains. Defaults to the system copy.
+    pub public_suffix_list: Option<String>,
+}
+
+pub struct SuffixList {
+    list: List,
+}
+
+impl SuffixList {
+    pub fn load(path: &str) -> Result<SuffixList, QueueConfigurationError> {
+        let contents = fs::read(path)
+            .map_err(|err| QueueConfigurationError::SuffixListError(path.to_string(), err.to_string()))?;
+        SuffixList::from_bytes(path, &contents)
+    }
+
+    /// Parses a list in the format of the public suffix list, naming it `source` in errors.
+    pub fn from_bytes(source: &str, contents: &[u8]) -> Result<SuffixList, QueueConfigurationError> {
+        let list = List::from_bytes(contents)
+            .map_err(|err| QueueConfigurationError::SuffixListError(source.to_string(), err.to_string()))?;
+        Ok(SuffixList { list })
+    }
+
+    /// Loads the configured list, or the system one when there is none. Without a list the
+    /// registrable domain of a host cannot be told, and guessing would let other people's
+    /// sites under a shared suffix like `co.uk` into the crawl, so it is an error.
+    pub fn for_policy(policy: &SitePolicy) -> Result<SuffixList, QueueConfigurationError> {
+        SuffixList::for_policy_or(policy, DEFAULT_PUBLIC_SUFFIX_LIST)
+    }
+
+    fn for_policy_or(policy: &SitePolicy, default: &str) -> Result<SuffixList, QueueConfigurationError> {
+        match &policy.public_suffix_list {
+            Some(path) => SuffixList::load(path),
+            None if Path::new(default).exists() => SuffixList::load(default),
+            None => Err(QueueConfigurationError::SuffixListError(
+                default.to_string(),
+                "no list found, which --include-subdomains needs. Give one with --public-suffix-list".to_string())),
+        }
+    }
+
+    pub fn registrable_domain(&self, host: &str) -> String {
+        self.list.domain(host.as_bytes())
+            .and_then(|domain| from_utf8(domain.as_bytes()).ok().map(|domain| domain.to_string()))
+            .unwrap_or_else(|| host.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::queue::QueueConfigurationError;
+    use crate::queue::site_policy::{SitePolicy, SuffixList};
+
+    #[test]
+    fn should_find_registrable_domain_using_public_suffix_list() {
+        let list = SuffixList::from_bytes("test", b"// ===BEGIN ICANN DOMAINS===\ncom\nuk\nco.uk\n// ===END ICANN DOMAINS===\n").unwrap();
+
+        assert_eq!(list.registrable_domain("docs.example.com"), "example.com");
+        assert_eq!(list.registrable_domain("www.shop.example.co.uk"), "example.co.uk");
+        assert_eq!(list.registrable_domain("co.uk"), "co.uk");
+    }
+
+    #[test]
+    fn should_refuse_to_guess_domains_without_public_suffix_list() {
+        let policy = SitePolicy { include_subdomains: true, ..SitePolicy::default() };
+
+        let list = SuffixList::for_policy_or(&policy, "/nonexistent/public_suffix_list.dat");
+
+        assert!(matches!(list.err(), Some(QueueConfigurationError::SuffixListError(path, _)) if path == "/nonexistent/public_suffix_list.dat"))
+    }
+}
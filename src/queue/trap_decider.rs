@@ -0,0 +1,208 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::queue::admission_observer::AdmissionObserver;
+use crate::queue::queue_addition_decider::{Decision, QueueAdditionDecider};
+
+/// Limits beyond which a link is taken to be a crawler trap. A limit of 0 turns its check off.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrapThresholds {
+    /// How many times the same segment may appear in a path, as in `/a/b/a/b/a/b/`.
+    pub max_segment_repeats: usize,
+    /// How many segments a path may have.
+    pub max_path_depth: usize,
+    /// How long a link may be.
+    pub max_url_length: usize,
+    /// How many distinct query strings are crawled for one path, as in `?page=1` to `?page=99999`.
+    pub max_query_variants: usize,
+}
+
+impl Default for TrapThresholds {
+    fn default() -> Self {
+        TrapThresholds {
+            max_segment_repeats: 3,
+            max_path_depth: 16,
+            max_url_length: 2048,
+            max_query_variants: 100,
+        }
+    }
+}
+
+/// The distinct query strings of the queued links, by host and path. Only queued links
+/// are counted, and no more than the limit for each path, so it grows with the pages of the
+/// crawl rather than with every link found.
+pub struct QueryVariants {
+    max_query_variants: usize,
+    variants: Mutex<HashMap<String, HashSet<String>>>,
+}
+
+impl QueryVariants {
+    fn new(max_query_variants: usize) -> QueryVariants {
+        QueryVariants { max_query_variants, variants: Mutex::new(HashMap::new()) }
+    }
+
+    /// The path of the link and its query, for links with a query when variants are limited.
+    fn split(&self, url: &Url) -> Option<(String, String)> {
+        let query = url.query().filter(|_| self.max_query_variants > 0)?;
+        Some((format!("{}{}", url.host_str().unwrap_or(""), url.path()), query.to_string()))
+    }
+
+    fn exceeded_by(&self, url: &Url) -> bool {
+        let (path, query) = match self.split(url) {
+            Some(split) => split,
+            None => return false,
+        };
+        self.variants.lock().unwrap().get(&path)
+            .map(|variants| !variants.contains(&query) && variants.len() >= self.max_query_variants)
+            .unwrap_or(false)
+    }
+}
+
+impl AdmissionObserver for QueryVariants {
+    fn queued(&self, link: &str) {
+        if let Some((path, query)) = Url::parse(link).ok().and_then(|url| self.split(&url)) {
+            let mut variants = self.variants.lock().unwrap();
+            let variants = variants.entry(path).or_default();
+            if variants.len() < self.max_query_variants {
+                variants.insert(query);
+            }
+        }
+    }
+}
+
+/// Rejects links that look like they were generated endlessly by calendars, faceted search
+/// or broken relative links. Query variants are counted by its observer as links are queued.
+pub struct TrapDecider {
+    thresholds: TrapThresholds,
+    query_variants: Arc<QueryVariants>,
+}
+
+impl TrapDecider {
+    pub fn new(thresholds: TrapThresholds) -> TrapDecider {
+        TrapDecider {
+            query_variants: Arc::new(QueryVariants::new(thresholds.max_query_variants)),
+            thresholds,
+        }
+    }
+
+    /// The observer counting the query variants of queued links for the decider.
+    pub fn query_variants(&self) -> Arc<QueryVariants> {
+        self.query_variants.clone()
+    }
+
+    fn detect(&self, link: &str) -> Option<String> {
+        let limits = &self.thresholds;
+        if limits.max_url_length > 0 && link.len() > limits.max_url_length {
+            return Some(format!("link is longer than {} characters", limits.max_url_length));
+        }
+        let url = Url::parse(link).ok()?;
+        let segments: Vec<&str> = url.path_segments()
+            .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+            .unwrap_or_default();
+        if limits.max_path_depth > 0 && segments.len() > limits.max_path_depth {
+            return Some(format!("path is deeper than {} segments", limits.max_path_depth));
+        }
+        if limits.max_segment_repeats > 0 {
+            let mut repeats: HashMap<&str, usize> = HashMap::new();
+            for segment in &segments {
+                let count = repeats.entry(segment).or_insert(0);
+                *count += 1;
+                if *count > limits.max_segment_repeats {
+                    return Some(format!("segment '{}' repeats more than {} times", segment, limits.max_segment_repeats));
+                }
+            }
+        }
+        if self.query_variants.exceeded_by(&url) {
+            return Some(format!("path has more than {} query variants", limits.max_query_variants));
+        }
+        None
+    }
+}
+
+impl QueueAdditionDecider for TrapDecider {
+    fn decide(&self, link: &str) -> Decision {
+        match self.detect(link) {
+            Some(reason) => {
+                log::info!("Skipping {} as a possible crawler trap: {}", link, reason);
+                Decision::reject("trap", reason)
+            }
+            None => Decision::accept("trap", "does not look like a crawler trap"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::queue::admission_observer::AdmissionObserver;
+    use crate::queue::queue_addition_decider::QueueAdditionDecider;
+    use crate::queue::trap_decider::{TrapDecider, TrapThresholds};
+
+    #[test]
+    fn should_allow_ordinary_links() {
+        let decider = TrapDecider::new(TrapThresholds::default());
+
+        assert!(decider.decide("https://example.com/docs/v2/intro.html?lang=en").accepted);
+    }
+
+    #[test]
+    fn should_reject_links_with_repeated_path_segments() {
+        let decider = TrapDecider::new(TrapThresholds { max_segment_repeats: 2, ..TrapThresholds::default() });
+
+        assert!(decider.decide("https://example.com/a/b/a/b/").accepted);
+        assert!(!decider.decide("https://example.com/a/b/a/b/a/b/").accepted);
+    }
+
+    #[test]
+    fn should_reject_deep_paths() {
+        let decider = TrapDecider::new(TrapThresholds { max_path_depth: 3, ..TrapThresholds::default() });
+
+        assert!(decider.decide("https://example.com/1/2/3").accepted);
+        assert!(!decider.decide("https://example.com/1/2/3/4").accepted);
+    }
+
+    #[test]
+    fn should_reject_long_links() {
+        let decider = TrapDecider::new(TrapThresholds { max_url_length: 30, ..TrapThresholds::default() });
+
+        assert!(decider.decide("https://example.com/short").accepted);
+        assert!(!decider.decide("https://example.com/a-much-longer-path").accepted);
+    }
+
+    #[test]
+    fn should_reject_query_variants_beyond_the_limit() {
+        let decider = TrapDecider::new(TrapThresholds { max_query_variants: 2, ..TrapThresholds::default() });
+        let query_variants = decider.query_variants();
+        query_variants.queued("https://example.com/list?page=1");
+        query_variants.queued("https://example.com/list?page=2");
+
+        assert!(decider.decide("https://example.com/list?page=1").accepted);
+        assert!(!decider.decide("https://example.com/list?page=99999").accepted);
+        assert!(decider.decide("https://example.com/other?page=99999").accepted)
+    }
+
+    #[test]
+    fn should_count_only_queued_query_variants() {
+        let decider = TrapDecider::new(TrapThresholds { max_query_variants: 1, ..TrapThresholds::default() });
+
+        assert!(decider.decide("https://example.com/list?page=1").accepted);
+        assert!(decider.decide("https://example.com/list?page=2").accepted);
+        decider.query_variants().queued("https://example.com/list?page=2");
+        assert!(!decider.decide("https://example.com/list?page=1").accepted)
+    }
+
+    #[test]
+    fn should_turn_checks_off_with_zero_limits() {
+        let decider = TrapDecider::new(TrapThresholds {
+            max_segment_repeats: 0,
+            max_path_depth: 0,
+            max_url_length: 0,
+            max_query_variants: 0,
+        });
+
+        assert!(decider.decide("https://example.com/a/a/a/a/a/a/a/a/a/a/a/a/a/a/a/a/a/a/a?x=1").accepted);
+    }
+}
@@ -0,0 +1,32 @@
+use std::fmt::{Display, Formatter};
+use std::error::Error;
+
+#[derive(Debug)]
+pub enum RecrawlError {
+    IOError(String),
+    FormatError(String),
+}
+
+impl Display for RecrawlError {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> std::fmt::Result {
+        let display_string = match self {
+            RecrawlError::IOError(reason) => format!("Could not read or write the recrawl state: {}", reason),
+            RecrawlError::FormatError(reason) => format!("Recrawl state is not in the expected format: {}", reason),
+        };
+        write!(fmt, "{}", display_string)
+    }
+}
+
+impl Error for RecrawlError {}
+
+impl From<std::io::Error> for RecrawlError {
+    fn from(err: std::io::Error) -> Self {
+        RecrawlError::IOError(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for RecrawlError {
+    fn from(err: serde_json::Error) -> Self {
+        RecrawlError::FormatError(err.to_string())
+    }
+}
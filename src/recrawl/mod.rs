@@ -0,0 +1,7 @@
+pub use error::RecrawlError;
+pub use page_history::PageHistory;
+pub use recrawl_client::RecrawlClient;
+
+mod error;
+mod page_history;
+mod recrawl_client;
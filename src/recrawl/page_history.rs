@@ -0,0 +1,265 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::client::{CrawlClientError, FetchedPage};
+use crate::link::Anchor;
+use crate::recrawl::RecrawlError;
+
+/// How a page compares to the crawl before.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Change {
+    New,
+    Changed,
+    Unchanged,
+    /// Not due for a visit yet, and taken to be unchanged.
+    Skipped,
+    /// The visit failed, and the page is kept as it was to be visited again.
+    Failed,
+    /// Not reached by this crawl, as when it stopped at `--max-pages`, and kept as it was.
+    Unvisited,
+    /// Answered with 404 or 410, and forgotten.
+    Removed,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Change::New => "new",
+            Change::Changed => "changed",
+            Change::Unchanged => "unchanged",
+            Change::Skipped => "skipped",
+            Change::Failed => "failed",
+            Change::Unvisited => "unvisited",
+            Change::Removed => "removed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// What is remembered of a page between crawls: the hash of its content, its links and where
+/// they are relative to, to follow them when the page is skipped, its fragments, and how many
+/// crawls to wait before visiting it again.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PageRecord {
+    pub content_hash: String,
+    #[serde(default)]
+    pub final_url: Option<String>,
+    pub anchors: Vec<Anchor>,
+    #[serde(default)]
+    pub fragments: Vec<String>,
+    pub interval: u64,
+    pub next_run: u64,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+struct HistoryFile {
+    run: u64,
+    pages: BTreeMap<String, PageRecord>,
+}
+
+/// The pages of the previous crawl, and what this crawl found of them. Pages that change are
+/// visited again on the next crawl, and pages that do not are visited half as often each
+/// time they are found unchanged, up to every `max_interval` crawls. A page is only
+/// forgotten once it answers 404 or 410.
+pub struct PageHistory {
+    run: u64,
+    max_interval: u64,
+    previous: BTreeMap<String, PageRecord>,
+    current: Mutex<HashMap<String, (Change, PageRecord)>>,
+}
+
+impl PageHistory {
+    pub fn new(previous: BTreeMap<String, PageRecord>, run: u64, max_interval: u64) -> PageHistory {
+        PageHistory { run, max_interval: max_interval.max(1), previous, current: Mutex::new(HashMap::new()) }
+    }
+
+    /// Loads the state saved by the previous crawl, starting afresh when there is none.
+    pub fn load(path: &str, max_interval: u64) -> Result<PageHistory, RecrawlError> {
+        let state: HistoryFile = if Path::new(path).exists() {
+            serde_json::from_reader(BufReader::new(File::open(path)?))?
+        } else {
+            HistoryFile::default()
+        };
+        Ok(PageHistory::new(state.pages, state.run + 1, max_interval))
+    }
+
+    /// The page as it was last seen, when it is not due for a visit in this crawl.
+    pub fn skip(&self, link: &str) -> Option<FetchedPage> {
+        let record = self.previous.get(link).filter(|record| record.next_run > self.run)?;
+        self.current.lock().unwrap().insert(link.to_string(), (Change::Skipped, record.clone()));
+        Some(FetchedPage {
+            anchors: record.anchors.clone(),
+            fragments: record.fragments.clone(),
+            content_hash: record.content_hash.clone(),
+            ..FetchedPage::new(record.final_url.as_deref().unwrap_or(link), vec![], b"")
+        })
+    }
+
+    /// Compares the page with the previous crawl, and schedules its next visit.
+    pub fn record(&self, link: &str, page: &FetchedPage) {
+        let (change, interval) = match self.previous.get(link) {
+            None => (Change::New, 1),
+            Some(record) if record.content_hash != page.content_hash => (Change::Changed, (record.interval / 2).max(1)),
+            Some(record) => (Change::Unchanged, (record.interval * 2).min(self.max_interval)),
+        };
+        let record = PageRecord {
+            content_hash: page.content_hash.clone(),
+            final_url: Some(page.final_url.clone()),
+            anchors: page.anchors.clone(),
+            fragments: page.fragments.clone(),
+            interval,
+            next_run: self.run + interval,
+        };
+        self.current.lock().unwrap().insert(link.to_string(), (change, record));
+    }
+
+    /// Notes a failed visit to a page of the previous crawl. Pages that answer 404 or 410 are
+    /// removed, others are kept as they were, to be visited again on the next crawl.
+    pub fn failed(&self, link: &str, err: &CrawlClientError) {
+        let record = match self.previous.get(link) {
+            Some(record) => record.clone(),
+            None => return,
+        };
+        let change = match err {
+            CrawlClientError::HttpError(404) | CrawlClientError::HttpError(410) => Change::Removed,
+            _ => Change::Failed,
+        };
+        self.current.lock().unwrap().insert(link.to_string(), (change, record));
+    }
+
+    /// Every page of this crawl and the previous one, with how it changed, ordered by link.
+    pub fn report(&self) -> Vec<(Change, String)> {
+        let current = self.current.lock().unwrap();
+        let mut report: Vec<(Change, String)> = current.iter()
+            .map(|(link, (change, _))| (*change, link.clone()))
+            .chain(self.previous.keys()
+                .filter(|link| !current.contains_key(*link))
+                .map(|link| (Change::Unvisited, link.clone())))
+            .collect();
+        report.sort_by(|(_, link), (_, other)| link.cmp(other));
+        report
+    }
+
+    /// Saves the pages found by this crawl, and those it did not reach, for the next one.
+    /// Removed pages are forgotten.
+    pub fn save(&self, path: &str) -> Result<(), RecrawlError> {
+        let current = self.current.lock().unwrap();
+        let mut pages: BTreeMap<String, PageRecord> = self.previous.iter()
+            .filter(|(link, _)| !current.contains_key(*link))
+            .map(|(link, record)| (link.clone(), record.clone()))
+            .collect();
+        pages.extend(current.iter()
+            .filter(|(_, (change, _))| *change != Change::Removed)
+            .map(|(link, (_, record))| (link.clone(), record.clone())));
+        let state = HistoryFile { run: self.run, pages };
+        let temporary = format!("{}.tmp", path);
+        fs::write(&temporary, serde_json::to_vec(&state)?)?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+
+    use crate::client::{CrawlClientError, FetchedPage};
+    use crate::recrawl::page_history::{Change, PageHistory, PageRecord};
+
+    fn record(content: &[u8], interval: u64, next_run: u64) -> PageRecord {
+        PageRecord { content_hash: FetchedPage::new("https://domain.com/", vec![], content).content_hash, final_url: None, anchors: vec![], fragments: vec![], interval, next_run }
+    }
+
+    fn previous() -> BTreeMap<String, PageRecord> {
+        vec![
+            ("https://domain.com/changed".to_string(), record(b"old", 4, 2)),
+            ("https://domain.com/unchanged".to_string(), record(b"same", 2, 2)),
+            ("https://domain.com/removed".to_string(), record(b"gone", 1, 2)),
+        ].into_iter().collect()
+    }
+
+    #[test]
+    fn should_report_new_changed_unchanged_and_removed_pages() {
+        let history = PageHistory::new(previous(), 2, 8);
+        history.record("https://domain.com/changed", &FetchedPage::new("https://domain.com/", vec![], b"new"));
+        history.record("https://domain.com/unchanged", &FetchedPage::new("https://domain.com/", vec![], b"same"));
+        history.record("https://domain.com/new", &FetchedPage::new("https://domain.com/", vec![], b"new"));
+        history.failed("https://domain.com/removed", &CrawlClientError::HttpError(410));
+
+        assert_eq!(history.report(), vec![
+            (Change::Changed, "https://domain.com/changed".to_string()),
+            (Change::New, "https://domain.com/new".to_string()),
+            (Change::Removed, "https://domain.com/removed".to_string()),
+            (Change::Unchanged, "https://domain.com/unchanged".to_string()),
+        ])
+    }
+
+    #[test]
+    fn should_visit_changing_pages_more_often_than_stable_ones() {
+        let history = PageHistory::new(previous(), 2, 8);
+        history.record("https://domain.com/changed", &FetchedPage::new("https://domain.com/", vec![], b"new"));
+        history.record("https://domain.com/unchanged", &FetchedPage::new("https://domain.com/", vec![], b"same"));
+
+        let current = history.current.lock().unwrap();
+        assert_eq!(current["https://domain.com/changed"].1.next_run, 4);
+        assert_eq!(current["https://domain.com/unchanged"].1.next_run, 6);
+    }
+
+    #[test]
+    fn should_skip_pages_not_due_and_keep_them_for_the_next_crawl() {
+        let mut previous = previous();
+        previous.insert("https://domain.com/stable".to_string(), record(b"stable", 8, 9));
+        let history = PageHistory::new(previous, 2, 8);
+
+        let skipped = history.skip("https://domain.com/stable");
+
+        assert_eq!(skipped.map(|page| page.content_hash), Some(record(b"stable", 8, 9).content_hash));
+        assert!(history.skip("https://domain.com/changed").is_none());
+        assert!(history.report().contains(&(Change::Skipped, "https://domain.com/stable".to_string())));
+    }
+
+    #[test]
+    fn should_keep_pages_that_failed_or_were_not_reached_for_the_next_crawl() {
+        let path = std::env::temp_dir().join(format!("crawly-history-kept-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let history = PageHistory::new(previous(), 2, 8);
+        history.failed("https://domain.com/changed", &CrawlClientError::HttpError(503));
+        history.failed("https://domain.com/removed", &CrawlClientError::HttpError(404));
+        history.save(path).unwrap();
+
+        assert_eq!(history.report(), vec![
+            (Change::Failed, "https://domain.com/changed".to_string()),
+            (Change::Removed, "https://domain.com/removed".to_string()),
+            (Change::Unvisited, "https://domain.com/unchanged".to_string()),
+        ]);
+        let next = PageHistory::load(path, 8).unwrap();
+        assert_eq!(next.previous.keys().cloned().collect::<Vec<String>>(), vec!["https://domain.com/changed", "https://domain.com/unchanged"]);
+        assert_eq!(next.previous["https://domain.com/changed"], record(b"old", 4, 2));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn should_pick_up_the_saved_state_in_the_next_crawl() {
+        let path = std::env::temp_dir().join(format!("crawly-history-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+        let first = PageHistory::load(path, 1).unwrap();
+        first.record("https://domain.com/", &FetchedPage::new("https://domain.com/", vec![], b"home"));
+        first.save(path).unwrap();
+
+        let second = PageHistory::load(path, 1).unwrap();
+        second.record("https://domain.com/", &FetchedPage::new("https://domain.com/", vec![], b"home"));
+
+        assert_eq!(second.run, 2);
+        assert_eq!(second.report(), vec![(Change::Unchanged, "https://domain.com/".to_string())]);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use crate::client::{CrawlClient, CrawlClientError, FetchedPage};
+use crate::recrawl::PageHistory;
+
+/// Wraps a client to compare every page it fetches with the previous crawl, and to answer
+/// for pages that are not due for a visit with what the previous crawl found.
+pub struct RecrawlClient<C: CrawlClient> {
+    client: C,
+    history: Arc<PageHistory>,
+}
+
+impl<C: CrawlClient> RecrawlClient<C> {
+    pub fn new(client: C, history: Arc<PageHistory>) -> RecrawlClient<C> {
+        RecrawlClient { client, history }
+    }
+}
+
+#[async_trait]
+impl<C: CrawlClient> CrawlClient for RecrawlClient<C> {
+    async fn crawl_and_fetch_links(&self, link: &str) -> Result<FetchedPage, CrawlClientError> {
+        if let Some(page) = self.history.skip(link) {
+            log::info!("Skipping {}, not due for a visit", link);
+            return Ok(page);
+        }
+        match self.client.crawl_and_fetch_links(link).await {
+            Ok(page) => {
+                self.history.record(link, &page);
+                Ok(page)
+            }
+            Err(err) => {
+                self.history.failed(link, &err);
+                Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    use mockall::predicate::eq;
+
+    use crate::client::{CrawlClient, CrawlClientError, FetchedPage, MockCrawlClient};
+    use crate::link::Anchor;
+    use crate::recrawl::{PageHistory, RecrawlClient};
+    use crate::recrawl::page_history::{Change, PageRecord};
+
+    #[tokio::test]
+    async fn should_fetch_due_pages_and_answer_for_the_others() {
+        let mut client = MockCrawlClient::new();
+        client.expect_crawl_and_fetch_links()
+            .with(eq("https://domain.com/due"))
+            .times(1)
+            .returning(|_| Ok(FetchedPage::new("https://domain.com/due", vec![], b"due")));
+        let stable = PageRecord {
+            content_hash: "hash".to_string(),
+            final_url: None,
+            anchors: vec![Anchor::new("/child", "child")],
+            fragments: vec![],
+            interval: 4,
+            next_run: 5,
+        };
+        let previous: BTreeMap<String, PageRecord> = vec![("https://domain.com/stable".to_string(), stable)].into_iter().collect();
+        let history = Arc::new(PageHistory::new(previous, 2, 8));
+        let client = RecrawlClient::new(client, history.clone());
+
+        let due = client.crawl_and_fetch_links("https://domain.com/due").await.unwrap();
+        let skipped = client.crawl_and_fetch_links("https://domain.com/stable").await.unwrap();
+
+        assert_eq!(due, FetchedPage::new("https://domain.com/due", vec![], b"due"));
+        assert_eq!(skipped.anchors, vec![Anchor::new("/child", "child")]);
+        assert_eq!(history.report(), vec![
+            (Change::New, "https://domain.com/due".to_string()),
+            (Change::Skipped, "https://domain.com/stable".to_string()),
+        ])
+    }
+
+    #[tokio::test]
+    async fn should_keep_pages_whose_visit_failed() {
+        let mut client = MockCrawlClient::new();
+        client.expect_crawl_and_fetch_links()
+            .with(eq("https://domain.com/flaky"))
+            .returning(|_| Err(CrawlClientError::HttpError(503)));
+        let flaky = PageRecord {
+            content_hash: "hash".to_string(),
+            final_url: None,
+            anchors: vec![],
+            fragments: vec![],
+            interval: 1,
+            next_run: 2,
+        };
+        let previous: BTreeMap<String, PageRecord> = vec![("https://domain.com/flaky".to_string(), flaky)].into_iter().collect();
+        let history = Arc::new(PageHistory::new(previous, 2, 8));
+        let client = RecrawlClient::new(client, history.clone());
+
+        let failed = client.crawl_and_fetch_links("https://domain.com/flaky").await;
+
+        assert_eq!(failed.err(), Some(CrawlClientError::HttpError(503)));
+        assert_eq!(history.report(), vec![(Change::Failed, "https://domain.com/flaky".to_string())])
+    }
+}
@@ -1,4 +1,5 @@
 use std::error::Error;
+use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use std::fmt::Debug;
 
@@ -27,3 +28,75 @@ impl<R: Clone + Sync + Send + Debug, E: Error + Sync + Send> ResultPublisher<R,
         Ok(result)
     }
 }
+
+#[async_trait]
+impl<R: Clone + Sync + Send + 'static, E: Error + Sync + Send + 'static, P: ResultPublisher<R, E>> ResultPublisher<R, E> for Arc<P> {
+    async fn notify(&self, result: R) -> Result<R, E> {
+        self.as_ref().notify(result).await
+    }
+}
+
+/// Passes every result on to each of its publishers, in order. Every publisher is notified
+/// even when one fails, and the first failure is returned.
+pub struct TeePublisher<R, E> {
+    publishers: Vec<Box<dyn ResultPublisher<R, E>>>,
+}
+
+impl<R, E> TeePublisher<R, E> {
+    pub fn new(publishers: Vec<Box<dyn ResultPublisher<R, E>>>) -> TeePublisher<R, E> {
+        TeePublisher {
+            publishers
+        }
+    }
+}
+
+#[async_trait]
+impl<R: Clone + Sync + Send + 'static, E: Error + Sync + Send + 'static> ResultPublisher<R, E> for TeePublisher<R, E> {
+    async fn notify(&self, result: R) -> Result<R, E> {
+        let mut failure = None;
+        for publisher in &self.publishers {
+            if let Err(err) = publisher.notify(result.clone()).await {
+                failure = failure.or(Some(err));
+            }
+        }
+        match failure {
+            Some(err) => Err(err),
+            None => Ok(result),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::{Display, Formatter};
+
+    use tokio::sync::mpsc::channel;
+
+    use crate::result_publisher::{MockResultPublisher, ResultPublisher, TeePublisher, TokioResultPublisher};
+
+    #[derive(Debug)]
+    struct Failed;
+
+    impl Display for Failed {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(f, "failed")
+        }
+    }
+
+    impl std::error::Error for Failed {}
+
+    #[tokio::test]
+    async fn should_notify_every_publisher_even_when_one_fails() {
+        let (tx, mut rx) = channel::<String>(8);
+        let mut failing = MockResultPublisher::<String, Failed>::new();
+        failing.expect_notify()
+            .times(1)
+            .returning(|_| Box::pin(futures::future::err(Failed)));
+        let tee = TeePublisher::new(vec![Box::new(failing), Box::new(TokioResultPublisher::new(tx))]);
+
+        let result = tee.notify("page".to_string()).await;
+
+        assert!(result.is_err());
+        assert_eq!(rx.recv().await, Some("page".to_string()))
+    }
+}
@@ -6,16 +6,15 @@ use crate::client::CrawlClientError;
 #[derive(Debug)]
 pub enum ScraperError {
     InvalidUrl(String),
-    ClientError,
+    ClientError(CrawlClientError),
 }
 
 impl Display for ScraperError {
     fn fmt(&self, fmt: &mut Formatter<'_>) -> std::fmt::Result {
-        let display_string = match self {
-            ScraperError::InvalidUrl(_) => "Invalid link or url".to_string(),
-            ScraperError::ClientError => "There was an error fetching from url".to_string(),
-        };
-        writeln!(fmt, "{:?}", display_string)
+        match self {
+            ScraperError::InvalidUrl(reason) => write!(fmt, "Invalid link or url{}", reason),
+            ScraperError::ClientError(err) => write!(fmt, "There was an error fetching from url: {}", err.message()),
+        }
     }
 }
 
@@ -28,7 +27,7 @@ impl From<LinkConstructionError> for ScraperError {
 }
 
 impl From<CrawlClientError> for ScraperError {
-    fn from(_: CrawlClientError) -> Self {
-        ScraperError::ClientError
+    fn from(err: CrawlClientError) -> Self {
+        ScraperError::ClientError(err)
     }
 }
\ No newline at end of file
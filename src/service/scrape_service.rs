@@ -1,9 +1,12 @@
-use crate::client::{CrawlClient};
-use crate::{LinkConstructor, result_publisher::ResultPublisher};
+use crate::client::{CrawlClient, CrawlClientError};
+use crate::{link::{split_fragment, LinkConstructor}, result_publisher::ResultPublisher};
 use std::convert::TryFrom;
+use std::time::Instant;
 
-use crate::service::error::ScraperError;
-use crate::queue::CrawlQueue;
+use crate::event::CrawlEvent;
+use crate::page::{Link, PageResult, Timings};
+use crate::service::ScraperError;
+use crate::queue::{Admission, CrawlQueue, FrontierEntry};
 use futures::{FutureExt, StreamExt};
 use futures::stream::FuturesUnordered;
 use std::iter::FromIterator;
@@ -12,18 +15,17 @@ use std::iter::FromIterator;
 #[async_trait]
 pub trait ScrapeService {
     fn has_more_items_to_scrape(&self) -> bool;
-    fn result(&self) -> Vec<String>;
 
-    async fn scrape_links(&self, links: Vec<String>) -> Result<Vec<String>, ScraperError>;
+    async fn scrape_links(&self, links: Vec<String>) -> Result<Vec<PageResult>, ScraperError>;
 }
 
-pub struct CrawleyScrapeService<C: CrawlClient, P: ResultPublisher<Vec<String>, ScraperError>> {
+pub struct CrawleyScrapeService<C: CrawlClient, P: ResultPublisher<CrawlEvent, ScraperError>> {
     client: C,
     queue: CrawlQueue,
     publisher: P,
 }
 
-impl<C: CrawlClient, P: ResultPublisher<Vec<String>, ScraperError>> CrawleyScrapeService<C, P> {
+impl<C: CrawlClient, P: ResultPublisher<CrawlEvent, ScraperError>> CrawleyScrapeService<C, P> {
     pub fn new(client: C, queue: CrawlQueue, publisher: P) -> CrawleyScrapeService<C, P> {
         CrawleyScrapeService {
             client,
@@ -33,47 +35,114 @@ impl<C: CrawlClient, P: ResultPublisher<Vec<String>, ScraperError>> CrawleyScrap
     }
 }
 
-impl <C: CrawlClient, P: ResultPublisher<Vec<String>, ScraperError>> CrawleyScrapeService<C, P> {
-    async fn scrape(&self, link: &str) -> Result<Vec<String>, ScraperError> {
-        let constructor = LinkConstructor::try_from(link)?;
-        let response = self.client.crawl_and_fetch_links(link).await;
+impl <C: CrawlClient, P: ResultPublisher<CrawlEvent, ScraperError>> CrawleyScrapeService<C, P> {
+    /// Visits the page, queues the links found on it, and publishes what was found. A page
+    /// that could not be fetched is published too, with its error.
+    async fn scrape(&self, entry: &FrontierEntry) -> PageResult {
+        self.notify(CrawlEvent::FetchStarted(entry.clone())).await;
+        let started = Instant::now();
+        let fetched = self.client.crawl_and_fetch_links(&entry.url).await;
+        let in_scope = match &fetched {
+            Ok(page) => self.follow_redirect(entry, &page.final_url).await,
+            Err(_) => true,
+        };
+        let mut result = match fetched {
+            Ok(page) if !in_scope => PageResult {
+                final_url: page.final_url,
+                status: page.status,
+                headers: page.headers,
+                timings: Timings { time_to_headers: page.time_to_headers, ..Timings::default() },
+                ..PageResult::new(entry)
+            },
+            Ok(page) => match LinkConstructor::try_from(page.final_url.as_str()) {
+                Ok(constructor) => {
+                    let outlinks: Vec<Link> = page.anchors
+                        .iter()
+                        .filter_map(|anchor| constructor.construct(&anchor.href).ok()
+                            .map(|link| Link::new(&link, &anchor.text)))
+                        .collect();
+                    let discovered = outlinks.iter()
+                        .map(|link| FrontierEntry::discovered(split_fragment(&link.url).0, entry, &link.text))
+                        .collect();
+                    self.publish(self.queue.add_entries(discovered).await).await;
+                    PageResult {
+                        final_url: page.final_url,
+                        status: page.status,
+                        headers: page.headers,
+                        outlinks,
+                        fragments: page.fragments,
+                        bytes: page.bytes,
+                        content_hash: Some(page.content_hash),
+                        timings: Timings { time_to_headers: page.time_to_headers, ..Timings::default() },
+                        ..PageResult::new(entry)
+                    }
+                }
+                Err(_) => PageResult::failed(entry, CrawlClientError::InvalidUri),
+            },
+            Err(err) => {
+                log::warn!("Unable to fetch {}: {}", entry.url, err);
+                PageResult::failed(entry, err)
+            }
+        };
+        result.timings.total = started.elapsed();
+        self.notify(CrawlEvent::fetched(result.clone())).await;
+        result
+    }
 
-        let links: Vec<String> = response?
-            .iter()
-            .filter_map(|href| constructor.construct(href).ok())
-            .collect();
-        let new_ones = self.queue.add_all(links.clone());
-        self.publisher.notify(new_ones).await
+    /// Whether the crawl takes in the page a link redirected to. A page out of scope is
+    /// published as rejected, and only the status of the visit is kept, without its content
+    /// and links.
+    async fn follow_redirect(&self, entry: &FrontierEntry, final_url: &str) -> bool {
+        if final_url == entry.url {
+            return true;
+        }
+        let decision = self.queue.redirected(final_url);
+        if !decision.accepted {
+            let redirect = FrontierEntry::discovered(final_url, entry, "redirect");
+            self.notify(CrawlEvent::UrlRejected { entry: redirect, decider: decision.decider, reason: decision.reason }).await;
+        }
+        decision.accepted
+    }
+
+    async fn publish(&self, admission: Admission) {
+        for entry in admission.queued {
+            self.notify(CrawlEvent::UrlQueued(entry)).await;
+        }
+        for (entry, decision) in admission.rejected {
+            self.notify(CrawlEvent::UrlRejected { entry, decider: decision.decider, reason: decision.reason }).await;
+        }
+    }
+
+    /// Publishing is left to the publisher. A publisher that fails does not stop the crawl.
+    async fn notify(&self, event: CrawlEvent) {
+        if let Err(err) = self.publisher.notify(event).await {
+            log::warn!("Unable to publish a crawl event: {}", err);
+        }
     }
 }
 
 #[async_trait]
-impl<C: CrawlClient, P: ResultPublisher<Vec<String>, ScraperError>> ScrapeService for CrawleyScrapeService<C, P> {
+impl<C: CrawlClient, P: ResultPublisher<CrawlEvent, ScraperError>> ScrapeService for CrawleyScrapeService<C, P> {
     fn has_more_items_to_scrape(&self) -> bool {
-        !self.queue.is_empty()
-    }
-
-    fn result(&self) -> Vec<String> {
-        self.queue.finished()
+        !self.queue.is_exhausted()
     }
 
-    async fn scrape_links(&self, links: Vec<String>) -> Result<Vec<String>, ScraperError> {
-        let items_added = self.queue.add_all(links.clone());
-        let unvisited_links = self.queue.items();
-        log::info!("Received {} Added {}", links.len(), items_added.len());
-        let futures: Vec<_> = unvisited_links.iter().map(|link| {
-            self.scrape(link)
-                .then(move |links| {
-                    self.queue.mark_as_done(link);
-                    futures::future::ready(links)
+    async fn scrape_links(&self, links: Vec<String>) -> Result<Vec<PageResult>, ScraperError> {
+        let admission = self.queue.add_entries(links.iter().map(|link| FrontierEntry::seed(link)).collect()).await;
+        log::info!("Received {} Added {}", links.len(), admission.queued.len());
+        self.publish(admission).await;
+        let unvisited_links = self.queue.next_batch();
+        let futures: Vec<_> = unvisited_links.iter().map(|entry| {
+            self.scrape(entry)
+                .then(move |result| {
+                    self.queue.mark_as_done(&entry.url);
+                    futures::future::ready(result)
                 })
         }).collect();
         let mut all_futures = FuturesUnordered::from_iter(futures);
-        let mut results: Vec<String> = vec![];
-        while let Some(res) = all_futures.next().await {
-            if let Ok(mut links) = res {
-                results.append(&mut links);
-            }
+        let mut results: Vec<PageResult> = vec![];
+        while let Some(result) = all_futures.next().await {
+            results.push(result);
         }
         Ok(results)
     }
@@ -81,11 +150,24 @@ impl<C: CrawlClient, P: ResultPublisher<Vec<String>, ScraperError>> ScrapeServic
 
 #[cfg(test)]
 mod tests {
-    use crate::client::MockCrawlClient;
+    use std::sync::{Arc, Mutex};
+
+    use crate::client::{CrawlClientError, FetchedPage, MockCrawlClient};
+    use crate::event::CrawlEvent;
+    use crate::page::{Link, PageResult};
     use crate::result_publisher::MockResultPublisher;
     use mockall::predicate::eq;
     use crate::service::{CrawleyScrapeService, ScraperError, ScrapeService};
-    use crate::queue::{CrawlQueue, create_queue};
+    use crate::queue::{CrawlQueue, create_queue, Decision, FrontierEntry, QueueSettings, ScopeSettings};
+    use crate::link::Anchor;
+
+    fn anchors(hrefs: Vec<&str>) -> FetchedPage {
+        FetchedPage::new("http://test.com/page1.html", hrefs.iter().map(|href| Anchor::new(href, "")).collect(), b"")
+    }
+
+    fn outlinks(result: &PageResult) -> Vec<String> {
+        result.outlinks.iter().map(|link| link.url.clone()).collect()
+    }
 
     #[tokio::test]
     async fn should_call_client_to_fetch_links_from_the_page() {
@@ -93,19 +175,18 @@ mod tests {
         client
             .expect_crawl_and_fetch_links()
             .with(eq("http://test.com/page1.html"))
-            .returning(|_| Ok(vec!["http://test.com/page2.html".to_string(), "https://github.com/test.html".to_string(), "http://test.com/page3.html".to_string()]));
-        let mut publisher = MockResultPublisher::<Vec<String>, ScraperError>::new();
+            .returning(|_| Ok(anchors(vec!["http://test.com/page2.html", "https://github.com/test.html", "http://test.com/page3.html"])));
+        let mut publisher = MockResultPublisher::<CrawlEvent, ScraperError>::new();
         publisher
             .expect_notify()
-            .with(eq(vec!["http://test.com/page2.html".to_string(), "https://github.com/test.html".to_string(), "http://test.com/page3.html".to_string()]))
-            .returning(|_| Box::pin(futures::future::ok(vec!["".to_string()])));
+            .returning(|event| Box::pin(futures::future::ok(event)));
 
         let service = CrawleyScrapeService::new(client, CrawlQueue::new(vec![]), publisher);
 
-        let result = service.scrape("http://test.com/page1.html").await;
+        let result = service.scrape(&FrontierEntry::seed("http://test.com/page1.html")).await;
 
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), vec![""])
+        assert_eq!(result.url, "http://test.com/page1.html");
+        assert_eq!(outlinks(&result), vec!["http://test.com/page2.html", "https://github.com/test.html", "http://test.com/page3.html"])
     }
 
     #[tokio::test]
@@ -114,19 +195,159 @@ mod tests {
         client
             .expect_crawl_and_fetch_links()
             .with(eq("http://test.com/page1.html"))
-            .returning(|_| Ok(vec!["page2.html".to_string(), "https://github.com/test.html".to_string(), "../page3.html".to_string()]));
-        let mut publisher = MockResultPublisher::<Vec<String>, ScraperError>::new();
+            .returning(|_| Ok(anchors(vec!["page2.html", "https://github.com/test.html", "../page3.html"])));
+        let mut publisher = MockResultPublisher::<CrawlEvent, ScraperError>::new();
         publisher
             .expect_notify()
-            .with(eq(vec!["http://test.com/page2.html".to_string(), "https://github.com/test.html".to_string(), "http://test.com/../page3.html".to_string()]))
-            .returning(|_| Box::pin(futures::future::ok(vec!["".to_string()])));
+            .returning(|event| Box::pin(futures::future::ok(event)));
 
         let service = CrawleyScrapeService::new(client, CrawlQueue::new(vec![]), publisher);
 
-        let result = service.scrape("http://test.com/page1.html").await;
+        let result = service.scrape(&FrontierEntry::seed("http://test.com/page1.html")).await;
 
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), vec![""])
+        assert_eq!(outlinks(&result), vec!["http://test.com/page2.html", "https://github.com/test.html", "http://test.com/../page3.html"]);
+        assert_eq!(result.outlinks[0], Link::new("http://test.com/page2.html", ""))
+    }
+
+    #[tokio::test]
+    async fn should_make_links_absolute_against_the_page_redirects_led_to() {
+        let mut client = MockCrawlClient::new();
+        client
+            .expect_crawl_and_fetch_links()
+            .with(eq("http://test.com/old/"))
+            .returning(|_| Ok(FetchedPage::new("http://test.com/new/", vec![Anchor::new("page.html", "page")], b"")));
+        let mut publisher = MockResultPublisher::<CrawlEvent, ScraperError>::new();
+        publisher
+            .expect_notify()
+            .returning(|event| Box::pin(futures::future::ok(event)));
+
+        let service = CrawleyScrapeService::new(client, CrawlQueue::new(vec![]), publisher);
+
+        let result = service.scrape(&FrontierEntry::seed("http://test.com/old/")).await;
+
+        assert_eq!(result.final_url, "http://test.com/new/");
+        assert_eq!(result.outlinks, vec![Link::new("http://test.com/new/page.html", "page")])
+    }
+
+    #[tokio::test]
+    async fn should_publish_pages_that_could_not_be_fetched_with_their_error() {
+        let mut client = MockCrawlClient::new();
+        client
+            .expect_crawl_and_fetch_links()
+            .returning(|_| Err(CrawlClientError::HttpError(404)));
+        let mut publisher = MockResultPublisher::<CrawlEvent, ScraperError>::new();
+        publisher
+            .expect_notify()
+            .withf(|event| matches!(event, CrawlEvent::FetchStarted(_) | CrawlEvent::FetchFailed(_)))
+            .times(2)
+            .returning(|event| Box::pin(futures::future::ok(event)));
+
+        let service = CrawleyScrapeService::new(client, CrawlQueue::new(vec![]), publisher);
+
+        let result = service.scrape(&FrontierEntry::seed("http://test.com/missing.html")).await;
+
+        assert_eq!(result.status, Some(404));
+        assert_eq!(result.error, Some(CrawlClientError::HttpError(404)))
+    }
+
+    #[tokio::test]
+    async fn should_publish_what_happens_to_the_page_and_its_links() {
+        let mut client = MockCrawlClient::new();
+        client
+            .expect_crawl_and_fetch_links()
+            .returning(|_| Ok(FetchedPage::new("http://test.com/", vec![Anchor::new("page.html", "page"), Anchor::new("https://github.com/", "github")], b"")));
+        let events = Arc::new(Mutex::new(vec![]));
+        let published = events.clone();
+        let mut publisher = MockResultPublisher::<CrawlEvent, ScraperError>::new();
+        publisher
+            .expect_notify()
+            .returning(move |event| {
+                published.lock().unwrap().push(event.clone());
+                Box::pin(futures::future::ok(event))
+            });
+        let service = CrawleyScrapeService::new(client, create_queue(&["http://test.com/".to_string()], ScopeSettings::default(), QueueSettings::default()).unwrap(), publisher);
+
+        let result = service.scrape_links(vec!["http://test.com/".to_string()]).await.unwrap();
+
+        let seed = FrontierEntry::seed("http://test.com/");
+        let events = events.lock().unwrap();
+        assert_eq!(events[0], CrawlEvent::UrlQueued(seed.clone()));
+        assert_eq!(events[1], CrawlEvent::FetchStarted(seed.clone()));
+        assert_eq!(events[2], CrawlEvent::UrlQueued(FrontierEntry::discovered("http://test.com/page.html", &seed, "page")));
+        assert!(matches!(&events[3], CrawlEvent::UrlRejected { entry, decider, .. } if entry.url == "https://github.com/" && decider == "same-site"));
+        assert_eq!(events[4], CrawlEvent::PageFetched(result[0].clone()));
+        assert_eq!(events.len(), 5)
+    }
+
+    #[tokio::test]
+    async fn should_leave_out_the_content_of_pages_redirected_out_of_scope() {
+        let mut client = MockCrawlClient::new();
+        client
+            .expect_crawl_and_fetch_links()
+            .returning(|_| Ok(FetchedPage { status: Some(200), ..FetchedPage::new("https://github.com/", vec![Anchor::new("/login", "login")], b"github") }));
+        let events = Arc::new(Mutex::new(vec![]));
+        let published = events.clone();
+        let mut publisher = MockResultPublisher::<CrawlEvent, ScraperError>::new();
+        publisher
+            .expect_notify()
+            .returning(move |event| {
+                published.lock().unwrap().push(event.clone());
+                Box::pin(futures::future::ok(event))
+            });
+        let service = CrawleyScrapeService::new(client, create_queue(&["http://test.com/".to_string()], ScopeSettings::default(), QueueSettings::default()).unwrap(), publisher);
+
+        let result = service.scrape(&FrontierEntry::seed("http://test.com/away")).await;
+
+        assert_eq!(result.final_url, "https://github.com/");
+        assert_eq!(result.status, Some(200));
+        assert_eq!(result.outlinks, vec![]);
+        assert_eq!(result.content_hash, None);
+        let events = events.lock().unwrap();
+        assert!(matches!(&events[1], CrawlEvent::UrlRejected { entry, decider, .. } if entry.url == "https://github.com/" && decider == "same-site"));
+        assert_eq!(events.len(), 3)
+    }
+
+    #[tokio::test]
+    async fn should_not_visit_pages_again_that_a_link_redirected_to() {
+        let mut client = MockCrawlClient::new();
+        client
+            .expect_crawl_and_fetch_links()
+            .returning(|_| Ok(FetchedPage::new("http://test.com/new/", vec![], b"")));
+        let mut publisher = MockResultPublisher::<CrawlEvent, ScraperError>::new();
+        publisher
+            .expect_notify()
+            .returning(|event| Box::pin(futures::future::ok(event)));
+        let service = CrawleyScrapeService::new(client, create_queue(&["http://test.com/".to_string()], ScopeSettings::default(), QueueSettings::default()).unwrap(), publisher);
+
+        service.scrape(&FrontierEntry::seed("http://test.com/old/")).await;
+
+        assert_eq!(service.queue.decide("http://test.com/new/"), Decision::reject("already-seen", "already crawled"))
+    }
+
+    #[tokio::test]
+    async fn should_queue_links_without_their_fragment_and_keep_it_in_the_outlinks() {
+        let mut client = MockCrawlClient::new();
+        client
+            .expect_crawl_and_fetch_links()
+            .returning(|_| Ok(FetchedPage { fragments: vec!["top".to_string()], ..FetchedPage::new("http://test.com/", vec![Anchor::new("page.html#intro", "intro"), Anchor::new("#top", "top")], b"") }));
+        let queued = Arc::new(Mutex::new(vec![]));
+        let published = queued.clone();
+        let mut publisher = MockResultPublisher::<CrawlEvent, ScraperError>::new();
+        publisher
+            .expect_notify()
+            .returning(move |event| {
+                if let CrawlEvent::UrlQueued(entry) = &event {
+                    published.lock().unwrap().push(entry.url.clone());
+                }
+                Box::pin(futures::future::ok(event))
+            });
+        let service = CrawleyScrapeService::new(client, create_queue(&["http://test.com/".to_string()], ScopeSettings::default(), QueueSettings::default()).unwrap(), publisher);
+
+        let result = service.scrape_links(vec!["http://test.com/".to_string()]).await.unwrap();
+
+        assert_eq!(*queued.lock().unwrap(), vec!["http://test.com/", "http://test.com/page.html"]);
+        assert_eq!(outlinks(&result[0]), vec!["http://test.com/page.html#intro", "http://test.com/#top"]);
+        assert_eq!(result[0].fragments, vec!["top".to_string()])
     }
 
     #[tokio::test]
@@ -135,28 +356,53 @@ mod tests {
         client
             .expect_crawl_and_fetch_links()
             .with(eq("http://test.com/base/page1.html"))
-            .returning(|_| Ok(vec!["page2.html".to_string(), "https://github.com/test.html".to_string(), "../page3.html".to_string()]));
+            .returning(|_| Ok(FetchedPage::new("http://test.com/base/page1.html", vec![Anchor::new("page2.html", ""), Anchor::new("https://github.com/test.html", ""), Anchor::new("../page3.html", "")], b"")));
         client
             .expect_crawl_and_fetch_links()
             .with(eq("http://test.com/base/page2.html"))
-            .returning(|_| Ok(vec!["page4.html".to_string(), "https://github.com/test.html".to_string(), "../page5.html".to_string()]));
+            .returning(|_| Ok(FetchedPage::new("http://test.com/base/page2.html", vec![Anchor::new("page4.html", ""), Anchor::new("https://github.com/test.html", ""), Anchor::new("../page5.html", "")], b"")));
         client
             .expect_crawl_and_fetch_links()
             .with(eq("http://test.com/base/page3.html"))
-            .returning(|_| Ok(vec!["page6.html".to_string(), "https://github.com/test.html".to_string(), "../page7.html".to_string()]));
-        let mut publisher = MockResultPublisher::<Vec<String>, ScraperError>::new();
+            .returning(|_| Ok(FetchedPage::new("http://test.com/base/page3.html", vec![Anchor::new("page6.html", ""), Anchor::new("https://github.com/test.html", ""), Anchor::new("../page7.html", "")], b"")));
+        let mut publisher = MockResultPublisher::<CrawlEvent, ScraperError>::new();
         publisher
             .expect_notify()
             .returning(|a| Box::pin(futures::future::ok(a)));
-        let service = CrawleyScrapeService::new(client, create_queue("http://test.com/").unwrap(), publisher);
+        let service = CrawleyScrapeService::new(client, create_queue(&["http://test.com/".to_string()], ScopeSettings::default(), QueueSettings::default()).unwrap(), publisher);
 
-        let result = service.scrape_links(vec![
+        let result = service.scrape_links([
             "http://test.com/base/page1.html",
             "http://test.com/base/page2.html",
             "http://test.com/base/page3.html"
         ].iter().map(|link| link.to_string()).collect()).await;
 
         assert!(result.is_ok());
-        assert_eq!(result.unwrap().len(), 5);
+        assert_eq!(result.unwrap().len(), 3);
+        assert!(service.has_more_items_to_scrape());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn should_scrape_queued_links_in_the_order_of_the_crawl_strategy() {
+        let mut client = MockCrawlClient::new();
+        client
+            .expect_crawl_and_fetch_links()
+            .with(eq("http://test.com/page1.html"))
+            .times(1)
+            .returning(|_| Ok(anchors(vec![])));
+        let mut publisher = MockResultPublisher::<CrawlEvent, ScraperError>::new();
+        publisher
+            .expect_notify()
+            .returning(|a| Box::pin(futures::future::ok(a)));
+        let settings = QueueSettings { batch_size: Some(1), ..QueueSettings::default() };
+        let service = CrawleyScrapeService::new(client, create_queue(&["http://test.com/".to_string()], ScopeSettings::default(), settings).unwrap(), publisher);
+
+        let result = service.scrape_links(vec![
+            "http://test.com/page1.html".to_string(),
+            "http://test.com/page2.html".to_string()
+        ]).await;
+
+        assert!(result.is_ok());
+        assert!(service.has_more_items_to_scrape());
+    }
+}